@@ -0,0 +1,86 @@
+// Compares the sequential and the parallel meshing systems on a grid of changed chunks.
+// Run with `cargo bench --features parallel`.
+
+use bevy::app::App;
+use bevy::asset::{AddAsset, AssetPlugin, Assets};
+use bevy::core::CorePlugin;
+use bevy::ecs::schedule::{Stage, SystemStage};
+use bevy::ecs::system::IntoSystem;
+use bevy::render::mesh::Mesh;
+use bevy::render::pipeline::PrimitiveTopology;
+use std::time::{Duration, Instant};
+use test_use_after_move::meshing::*;
+
+const GRID : usize = 8;
+const RUNS : u32 = 10;
+
+fn terrain(seed : usize) -> Chunk16 {
+    let mut chunk = Chunk16::empty();
+    for index in 0..16 * 16 * 16 {
+        let (x, y, z) = Chunk16::coords(index);
+        if y <= (x * 3 + z * 5 + seed * 7) % 11 {
+            chunk.set_block(x, y, z, Block { material : MaterialID::new(1 + ((x + y + z + seed) % 3) as u32) });
+        }
+    }
+    chunk
+}
+
+// A grid of linked chunks that all need meshing
+fn app() -> App {
+    let mut registry = MaterialRegistry::new();
+    for id in 1..=3 {
+        registry.register(Material::solid(MaterialID::new(id)));
+    }
+
+    let mut builder = App::build();
+    builder
+        .insert_resource(registry)
+        .insert_resource(MaterialAtlas::default())
+        .add_plugin(CorePlugin)
+        .add_plugin(AssetPlugin)
+        .add_asset::<Mesh>();
+    let mut app = builder.app;
+
+    let mut entities = Vec::new();
+    for i in 0..GRID * GRID {
+        let mesh = app.world.get_resource_mut::<Assets<Mesh>>().unwrap().add(Mesh::new(PrimitiveTopology::TriangleList));
+        entities.push(app.world.spawn().insert(terrain(i)).insert(mesh).id());
+    }
+    for x in 0..GRID {
+        for z in 0..GRID {
+            if x + 1 < GRID {
+                link_chunks::<16, 16, 16>(&mut app.world, entities[x * GRID + z], entities[(x + 1) * GRID + z], AADirection::XPositive);
+            }
+            if z + 1 < GRID {
+                link_chunks::<16, 16, 16>(&mut app.world, entities[x * GRID + z], entities[x * GRID + z + 1], AADirection::ZPositive);
+            }
+        }
+    }
+    app
+}
+
+// Average time of meshing the whole grid once with the stage `make_stage` returns
+fn bench(name : &str, make_stage : fn() -> SystemStage) {
+    let mut total = Duration::default();
+    for _ in 0..RUNS {
+        let mut app = app();
+        let mut stage = make_stage();
+        let start = Instant::now();
+        stage.run(&mut app.world);
+        total += start.elapsed();
+    }
+    println!("{:<12} {:>8.2} ms per {} chunks", name, total.as_secs_f64() * 1000f64 / RUNS as f64, GRID * GRID);
+}
+
+fn main() {
+    bench("sequential", || {
+        let mut stage = SystemStage::single_threaded();
+        stage.add_system(chunk_meshing_system::<GreedyChunkMesher, 16, 16, 16>.system());
+        stage
+    });
+    bench("parallel", || {
+        let mut stage = SystemStage::single_threaded();
+        stage.add_system(parallel_chunk_meshing_system::<GreedyChunkMesher, 16, 16, 16>.system());
+        stage
+    });
+}
@@ -1,5 +1,3 @@
-mod meshing;
-
 fn main() {
     let x = 5u32;
     let y = x + 1;
@@ -1,48 +1,264 @@
-use bevy::ecs::world::World;
 use bevy::render::mesh::{Indices, Mesh, VertexAttributeValues};
 use bevy::render::pipeline::PrimitiveTopology;
-use nalgebra::{point, vector, Point2, Point3, Vector2};
-use bevy::ecs::prelude::{Res, With};
-use bevy::asset::{Handle, Asset, Assets};
-use bevy::ecs::system::{ResMut, Query};
-use array_macro::array;
-use std::slice::Iter;
-use std::borrow::Cow;
+use nalgebra::{point, vector, Point3, Vector2, Vector3};
+use bevy::asset::{Handle, Assets};
+use bevy::ecs::bundle::Bundle;
+use bevy::ecs::system::{Commands, Local, Res, ResMut, Query};
+use bevy::ecs::query::{Changed, Without};
 use bevy::ecs::entity::Entity;
+use bevy::ecs::world::{Mut, World};
+use bevy::log::{debug, warn};
+#[cfg(feature = "parallel")]
+use bevy::tasks::ComputeTaskPool;
+use bevy::tasks::{AsyncComputeTaskPool, Task};
+use std::any::{Any, TypeId};
+use std::collections::hash_map::{DefaultHasher, Entry};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::hash::{Hash, Hasher};
+use std::marker::PhantomData;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, OnceLock, PoisonError};
+use std::sync::atomic::{AtomicBool, Ordering};
 
 #[derive(Eq, PartialEq, Ord, PartialOrd, Copy, Clone, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct MaterialID {
     id: u32,
 }
 
 impl From<u32> for MaterialID {
     fn from(id: u32) -> Self {
-        Self::from(id)
+        Self::new(id)
+    }
+}
+
+// For compact material palettes
+impl From<u16> for MaterialID {
+    fn from(id: u16) -> Self {
+        Self::new(id as u32)
+    }
+}
+
+impl From<u8> for MaterialID {
+    fn from(id: u8) -> Self {
+        Self::new(id as u32)
     }
 }
 
 impl MaterialID {
-    const fn from(id: u32) -> Self {
+    pub const fn new(id: u32) -> Self {
         Self {
             id
         }
     }
+
+    pub const fn id(&self) -> u32 {
+        self.id
+    }
 }
 
-#[derive(Eq, PartialEq, Debug, Clone)]
+// Light level of blocks that are fully lit
+pub const MAX_LIGHT : u8 = 15;
+
+// Vertex color of materials that aren't tinted
+pub const WHITE : [f32; 4] = [1f32, 1f32, 1f32, 1f32];
+
+#[derive(PartialEq, Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Material {
     pub id: MaterialID,
     pub transparent : bool,
     pub custom_model : bool,
+    // RGBA color multiplied into the vertex colors of the material's faces
+    pub tint : [f32; 4],
+}
+
+impl Material {
+
+    // An untinted material that hides the faces of its neighbors
+    pub const fn solid(id : MaterialID) -> Self {
+        Material {
+            id,
+            transparent : false,
+            custom_model : false,
+            tint : WHITE,
+        }
+    }
+
+    // An untinted material that goes into the transparent mesh, like glass or water
+    pub const fn transparent(id : MaterialID) -> Self {
+        Material {
+            id,
+            transparent : true,
+            custom_model : false,
+            tint : WHITE,
+        }
+    }
 }
 
 pub const AIR : Material = Material {
-    id : MaterialID::from(0),
+    id : MaterialID::new(0),
     transparent : true,
     custom_model : false,
+    tint : WHITE,
+};
+
+// Stands in for materials that aren't registered, so that blocks with unknown ids show up
+// in bright magenta instead of failing to mesh. Its id is never looked up.
+pub const MISSING : Material = Material {
+    id : MaterialID::new(u32::MAX),
+    transparent : false,
+    custom_model : false,
+    tint : [1f32, 0f32, 1f32, 1f32],
 };
 
+// Looks up the full `Material` for the ids stored in blocks. `AIR` is always registered,
+// and id 0 is reserved for it: culling treats blocks of id 0 as empty space. Ids that
+// aren't registered get the properties of the fallback material, `MISSING` by default.
+#[derive(Clone)]
+pub struct MaterialRegistry {
+    materials : HashMap<MaterialID, Material>,
+    models : HashMap<MaterialID, Mesh>,
+    fallback : Option<Material>,
+    face_visibility : Option<FaceVisibility>,
+}
+
+// Decides whether the face of the first block that points at the second one in the given
+// direction is drawn. See `MaterialRegistry::set_face_visibility`.
+pub type FaceVisibility = Arc<dyn Fn(&Block, &Block, AADirection) -> bool + Send + Sync>;
+
+impl Default for MaterialRegistry {
+    fn default() -> Self {
+        let mut materials = HashMap::new();
+        materials.insert(AIR.id, AIR);
+
+        MaterialRegistry {
+            materials,
+            models : HashMap::new(),
+            fallback : Some(MISSING),
+            face_visibility : None,
+        }
+    }
+}
+
+impl MaterialRegistry {
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Returns false without registering anything if the material uses `AIR`'s id
+    pub fn register(&mut self, material : Material) -> bool {
+        if material.id == AIR.id {
+            return false;
+        }
+        self.materials.insert(material.id, material);
+        true
+    }
+
+    pub fn get(&self, id : MaterialID) -> Option<&Material> {
+        self.materials.get(&id)
+    }
+
+    pub fn contains(&self, id : MaterialID) -> bool {
+        self.materials.contains_key(&id)
+    }
+
+    // Without a fallback, meshing chunks with unregistered materials fails with
+    // `MeshError::UnknownMaterial`
+    pub fn set_fallback(&mut self, fallback : Option<Material>) {
+        self.fallback = fallback;
+    }
+
+    pub fn fallback(&self) -> Option<&Material> {
+        self.fallback.as_ref()
+    }
+
+    // The registered material, or the fallback for unknown ids
+    fn resolve(&self, id : MaterialID) -> Option<&Material> {
+        self.materials.get(&id).or(self.fallback.as_ref())
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Material> {
+        self.materials.values()
+    }
+
+    pub fn is_transparent(&self, id : MaterialID) -> bool {
+        self.resolve(id).map(|material| material.transparent).unwrap_or(false)
+    }
+
+    pub fn has_custom_model(&self, id : MaterialID) -> bool {
+        self.resolve(id).map(|material| material.custom_model).unwrap_or(false)
+    }
+
+    // Whether blocks of this material fill their whole cell, i.e. they hide the faces
+    // of their neighbors and take part in collision
+    pub fn is_solid(&self, id : MaterialID) -> bool {
+        id != AIR.id && !self.is_transparent(id) && !self.has_custom_model(id)
+    }
+
+    // Whether a block of material `neighbor` hides the face of a `material` block that
+    // points at it. Faces are hidden by solid blocks, and between two blocks of the same
+    // transparent material. Custom models never hide their neighbors' faces.
+    pub fn hides_face(&self, material : MaterialID, neighbor : MaterialID) -> bool {
+        self.is_solid(neighbor) || (neighbor == material && !self.has_custom_model(neighbor))
+    }
+
+    // Replaces the culling of faces between two blocks. Without a predicate, faces are drawn
+    // unless `hides_face` says otherwise. Faces on the chunk border without a neighbor chunk,
+    // and meshes generated without `cull_interior`, are unaffected.
+    pub fn set_face_visibility(
+        &mut self,
+        predicate : impl Fn(&Block, &Block, AADirection) -> bool + Send + Sync + 'static
+    ) {
+        self.face_visibility = Some(Arc::new(predicate));
+    }
+
+    pub fn clear_face_visibility(&mut self) {
+        self.face_visibility = None;
+    }
+
+    // Whether the face of `block` pointing at `neighbor` in `direction` is drawn
+    pub fn draws_face(&self, block : &Block, neighbor : &Block, direction : AADirection) -> bool {
+        match &self.face_visibility {
+            Some(predicate) => predicate(block, neighbor, direction),
+            None => !self.hides_face(block.material, neighbor.material),
+        }
+    }
+
+    pub fn tint(&self, id : MaterialID) -> [f32; 4] {
+        self.resolve(id).map(|material| material.tint).unwrap_or(WHITE)
+    }
+
+    // Sets the mesh that is copied into the chunk mesh for every block of a custom model
+    // material. The model's coordinates are relative to the block's minimum corner. It
+    // needs positions, and may have normals, UVs and indices.
+    pub fn register_model(&mut self, id : MaterialID, model : Mesh) {
+        self.models.insert(id, model);
+    }
+
+    pub fn model(&self, id : MaterialID) -> Option<&Mesh> {
+        self.models.get(&id)
+    }
+}
+
 #[derive(Eq, PartialEq, Debug, Copy, Clone, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Axis {
+    X = 0,
+    Y,
+    Z,
+}
+
+impl Axis {
+
+    // All axes in discriminant order, so `ALL[i]` is the axis of the i-th coordinate
+    pub const ALL : [Axis; 3] = [Axis::X, Axis::Y, Axis::Z];
+}
+
+#[derive(Eq, PartialEq, Debug, Copy, Clone, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum AADirection {
     XPositive = 0,
     XNegative,
@@ -54,6 +270,20 @@ pub enum AADirection {
 
 impl AADirection {
 
+    // All directions in discriminant order, matching the indexing of `Chunk::neighbors`
+    pub const ALL : [AADirection; 6] = [
+        AADirection::XPositive,
+        AADirection::XNegative,
+        AADirection::YPositive,
+        AADirection::YNegative,
+        AADirection::ZPositive,
+        AADirection::ZNegative,
+    ];
+
+    pub fn iter() -> impl Iterator<Item = AADirection> {
+        Self::ALL.iter().copied()
+    }
+
     pub fn is_positive(&self) -> bool {
         match self {
             AADirection::XPositive => true,
@@ -68,13 +298,183 @@ impl AADirection {
     pub fn is_negative(&self) -> bool {
         !self.is_positive()
     }
+
+    pub fn axis(&self) -> Axis {
+        match self {
+            AADirection::XPositive | AADirection::XNegative => Axis::X,
+            AADirection::YPositive | AADirection::YNegative => Axis::Y,
+            AADirection::ZPositive | AADirection::ZNegative => Axis::Z,
+        }
+    }
+
+    // The direction along `axis`, towards positive or negative coordinates
+    pub fn from_axis_sign(axis : Axis, positive : bool) -> AADirection {
+        match (axis, positive) {
+            (Axis::X, true) => AADirection::XPositive,
+            (Axis::X, false) => AADirection::XNegative,
+            (Axis::Y, true) => AADirection::YPositive,
+            (Axis::Y, false) => AADirection::YNegative,
+            (Axis::Z, true) => AADirection::ZPositive,
+            (Axis::Z, false) => AADirection::ZNegative,
+        }
+    }
+
+    pub fn opposite(&self) -> AADirection {
+        match self {
+            AADirection::XPositive => AADirection::XNegative,
+            AADirection::XNegative => AADirection::XPositive,
+            AADirection::YPositive => AADirection::YNegative,
+            AADirection::YNegative => AADirection::YPositive,
+            AADirection::ZPositive => AADirection::ZNegative,
+            AADirection::ZNegative => AADirection::ZPositive,
+        }
+    }
+
+    // The step to the adjacent block in this direction
+    pub fn offset(&self) -> (i32, i32, i32) {
+        match self {
+            AADirection::XPositive => (1, 0, 0),
+            AADirection::XNegative => (-1, 0, 0),
+            AADirection::YPositive => (0, 1, 0),
+            AADirection::YNegative => (0, -1, 0),
+            AADirection::ZPositive => (0, 0, 1),
+            AADirection::ZNegative => (0, 0, -1),
+        }
+    }
+
+    pub fn normal(&self) -> [f32; 3] {
+        match self {
+            AADirection::XPositive => [1f32, 0f32, 0f32],
+            AADirection::XNegative => [-1f32, 0f32, 0f32],
+            AADirection::YPositive => [0f32, 1f32, 0f32],
+            AADirection::YNegative => [0f32, -1f32, 0f32],
+            AADirection::ZPositive => [0f32, 0f32, 1f32],
+            AADirection::ZNegative => [0f32, 0f32, -1f32],
+        }
+    }
 }
 
-#[derive(Clone)]
+// A sub-rectangle of a texture atlas in UV space
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub struct UvRect {
+    pub min : [f32; 2],
+    pub max : [f32; 2],
+}
+
+impl UvRect {
+
+    pub const FULL : UvRect = UvRect {
+        min : [0f32, 0f32],
+        max : [1f32, 1f32],
+    };
+
+    pub fn new(min : [f32; 2], max : [f32; 2]) -> Self {
+        UvRect {
+            min,
+            max,
+        }
+    }
+
+    // Maps a UV from the unit square into this rectangle
+    pub fn map(&self, uv : [f32; 2]) -> [f32; 2] {
+        [
+            self.min[0] + uv[0] * (self.max[0] - self.min[0]),
+            self.min[1] + uv[1] * (self.max[1] - self.min[1]),
+        ]
+    }
+
+    // Moves every edge inwards by `amount`
+    pub fn inset(&self, amount : [f32; 2]) -> UvRect {
+        UvRect {
+            min : [self.min[0] + amount[0], self.min[1] + amount[1]],
+            max : [self.max[0] - amount[0], self.max[1] - amount[1]],
+        }
+    }
+}
+
+#[derive(Copy, Clone)]
+struct MaterialTiles {
+    default : UvRect,
+    faces : [Option<UvRect>; 6],
+}
+
+// Maps materials to their tiles in the texture atlas. A material has one tile for all
+// of its faces, which can be overridden per face direction (e.g. grass tops). Materials
+// without a tile use the whole texture. Quads merged by the greedy mesher repeat their
+// tile once per block, so their UVs extend past the tile and have to be wrapped into it
+// by the shader.
+#[derive(Default, Clone)]
+pub struct MaterialAtlas {
+    tiles : HashMap<MaterialID, MaterialTiles>,
+    // Pulls the UVs of every tile away from its edges, in UV units
+    inset : [f32; 2],
+}
+
+impl MaterialAtlas {
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn tiles_mut(&mut self, material : MaterialID) -> &mut MaterialTiles {
+        self.tiles.entry(material).or_insert(MaterialTiles {
+            default : UvRect::FULL,
+            faces : [None; 6],
+        })
+    }
+
+    pub fn insert(&mut self, material : MaterialID, rect : UvRect) {
+        self.tiles_mut(material).default = rect;
+    }
+
+    pub fn insert_face(&mut self, material : MaterialID, direction : AADirection, rect : UvRect) {
+        self.tiles_mut(material).faces[direction as usize] = Some(rect);
+    }
+
+    // Keeps filtering from sampling the neighboring tiles by moving the edges of every tile
+    // `texels` texels inwards, half a texel being the usual choice. `texture_size` is the
+    // size of the atlas texture in texels.
+    pub fn set_inset(&mut self, texels : f32, texture_size : [u32; 2]) {
+        self.inset = [texels / texture_size[0] as f32, texels / texture_size[1] as f32];
+    }
+
+    pub fn inset(&self) -> [f32; 2] {
+        self.inset
+    }
+
+    pub fn rect(&self, material : MaterialID, direction : AADirection) -> UvRect {
+        self.tiles
+            .get(&material)
+            .map(|tiles| tiles.faces[direction as usize].unwrap_or(tiles.default))
+            .unwrap_or(UvRect::FULL)
+            .inset(self.inset)
+    }
+}
+
+// Blocks compare equal when all of their fields do. Light levels live in the chunk, so
+// blocks of the same material are equal.
+#[derive(Eq, PartialEq, Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Block {
     pub material : MaterialID,
 }
 
+impl Block {
+
+    pub fn is_air(&self) -> bool {
+        self.material == AIR.id
+    }
+
+    // Whether the block hides the faces of the blocks next to it
+    pub fn is_opaque(&self, registry : &MaterialRegistry) -> bool {
+        registry.is_solid(self.material)
+    }
+}
+
+static AIR_BLOCK : Block = Block {
+    material : AIR.id,
+};
+
 impl Default for Block {
     fn default() -> Self {
         Block {
@@ -83,24 +483,64 @@ impl Default for Block {
     }
 }
 
-pub struct Chunk {
+pub struct Chunk<const X: usize = 16, const Y: usize = 16, const Z: usize = 16> {
 
-    blocks : [Block; Chunk::SIZE_X * Chunk::SIZE_Y * Chunk::SIZE_Z],
+    blocks : Box<[Block]>,
+    // Number of blocks that aren't air. Unknown after the blocks were handed out mutably,
+    // until it is recounted.
+    solid_count : Option<usize>,
     updated : bool,
+    changed_edges : [bool; 6],
     neighbors : [Option<Entity>; 6],
+    // Set by the meshing systems, which only get shared access to chunks
+    mesh_failed : AtomicBool,
+    mesh_queued : AtomicBool,
+    // Inclusive bounds of the blocks modified since the last `clear_change`
+    dirty_region : Option<(Point3<usize>, Point3<usize>)>,
+    // Light level of every block, only allocated once a light level is set
+    light : Option<Box<[u8]>>,
+    // Bumped on every change, see `revision`
+    revision : u64,
+}
+
+pub type Chunk16 = Chunk<16, 16, 16>;
+
+// Clones get no neighbors, the entities they point to belong to the original chunk
+impl<const X: usize, const Y: usize, const Z: usize> Clone for Chunk<X, Y, Z> {
+    fn clone(&self) -> Self {
+        Chunk {
+            blocks : self.blocks.clone(),
+            solid_count : self.solid_count,
+            updated : self.updated,
+            changed_edges : self.changed_edges,
+            neighbors : [None; 6],
+            mesh_failed : AtomicBool::new(false),
+            mesh_queued : AtomicBool::new(false),
+            dirty_region : self.dirty_region,
+            light : self.light.clone(),
+            revision : self.revision,
+        }
+    }
 }
 
-impl Chunk {
+impl<const X: usize, const Y: usize, const Z: usize> Chunk<X, Y, Z> {
 
-    pub const SIZE_X : usize = 16;
-    pub const SIZE_Y : usize = 16;
-    pub const SIZE_Z : usize = 16;
+    pub const SIZE_X : usize = X;
+    pub const SIZE_Y : usize = Y;
+    pub const SIZE_Z : usize = Z;
 
     pub fn empty() -> Self {
         Chunk {
-            blocks : array![Block::default(); Chunk::SIZE_X * Chunk::SIZE_Y * Chunk::SIZE_Z],
+            blocks : vec![Block::default(); X * Y * Z].into_boxed_slice(),
+            solid_count : Some(0),
             updated : true,
+            changed_edges : [false; 6],
             neighbors : [None; 6],
+            mesh_failed : AtomicBool::new(false),
+            mesh_queued : AtomicBool::new(false),
+            dirty_region : Some(Self::whole_region()),
+            light : None,
+            revision : 0,
         }
     }
 
@@ -108,6 +548,15 @@ impl Chunk {
         self.neighbors[direction as usize] = Some(entity)
     }
 
+    // Detaches the neighbor, e.g. when it is unloaded. Returns the entity that was linked.
+    pub fn remove_neighbor(&mut self, direction : AADirection) -> Option<Entity> {
+        self.neighbors[direction as usize].take()
+    }
+
+    pub fn clear_neighbors(&mut self) {
+        self.neighbors = [None; 6];
+    }
+
     pub fn get_neighbor(&self, direction : AADirection) -> Option<Entity> {
         self.neighbors[direction as usize]
     }
@@ -116,165 +565,6789 @@ impl Chunk {
         &self.neighbors
     }
 
+    // The neighbors that are set, with the direction they are in
+    pub fn iter_neighbors(&self) -> impl Iterator<Item = (AADirection, Entity)> + '_ {
+        AADirection::iter()
+            .zip(self.neighbors.iter())
+            .filter_map(|(direction, neighbor)| neighbor.map(|neighbor| (direction, neighbor)))
+    }
+
+    // For setting several neighbors at once, indexed by `AADirection as usize`
+    pub fn neighbors_mut(&mut self) -> &mut [Option<Entity>; 6] {
+        &mut self.neighbors
+    }
+
+    pub fn size() -> Vector3<usize> {
+        vector![X, Y, Z]
+    }
+
+    pub fn index(x : usize, y : usize, z : usize) -> usize {
+        X * (Y * z + y) + x
+    }
+
+    pub fn coords(index : usize) -> (usize, usize, usize) {
+        (index % X, (index / X) % Y, index / (X * Y))
+    }
+
+    pub fn try_block_at(&self, x : usize, y : usize, z : usize) -> Option<&Block> {
+        if x >= X || y >= Y || z >= Z {
+            return None;
+        }
+        self.blocks.get(Self::index(x, y, z))
+    }
+
+    pub fn try_block_at_mut(&mut self, x : usize, y : usize, z : usize) -> Option<&mut Block> {
+        if x >= X || y >= Y || z >= Z {
+            return None;
+        }
+        self.solid_count = None;
+        self.blocks.get_mut(Self::index(x, y, z))
+    }
+
     pub fn block_at(&self, x : usize, y : usize, z : usize) -> &Block {
-        self.blocks
-            .get(Chunk::SIZE_X * (Chunk::SIZE_Y * z + y) + x)
+        self.try_block_at(x, y, z)
             .expect("Block index out of range")
     }
 
     pub fn block_at_mut(&mut self, x : usize, y : usize, z : usize) -> &mut Block {
-        self.blocks
-            .get_mut(Chunk::SIZE_X * (Chunk::SIZE_Y * z + y) + x)
+        self.try_block_at_mut(x, y, z)
             .expect("Block index out of range")
     }
 
-    pub fn has_changed(&self) -> bool {
-        self.updated
+    // Like `try_block_at`, for coordinates computed by stepping from a block, which may be
+    // negative. See `get_block_world` to look into the neighbors instead.
+    pub fn block_at_signed(&self, x : i32, y : i32, z : i32) -> Option<&Block> {
+        if x < 0 || y < 0 || z < 0 {
+            return None;
+        }
+        self.try_block_at(x as usize, y as usize, z as usize)
     }
 
-    pub fn set_change(&mut self) {
-        self.updated = true
+    // Looks up a block by coordinates relative to this chunk that may lie up to one chunk
+    // outside of it, in the neighbor on that side. Missing neighbors read as air.
+    pub fn get_block_world<'a>(
+        &'a self,
+        neighbors : &ChunkNeighborhood<'a, X, Y, Z>,
+        x : i32,
+        y : i32,
+        z : i32
+    ) -> &'a Block {
+        locate_world::<X, Y, Z, _>(self, neighbors, [x, y, z])
+            .map(|(chunk, local)| chunk.block_at(local[0], local[1], local[2]))
+            .unwrap_or(&AIR_BLOCK)
     }
 
-    pub fn clear_change(&mut self) {
-        self.updated = false
+    // Materials of the outermost layer of blocks on the side facing `direction`, row by row
+    // in the same u/v layout as the greedy mesher's face planes. Const generics can't size
+    // an array by X * Z yet, so the layer is returned as a `Vec`.
+    pub fn boundary_layer(&self, direction : AADirection) -> Vec<MaterialID> {
+        let (slices, size_u, size_v) = plane_size(Self::size(), direction);
+        let slice = if direction.is_positive() { slices - 1 } else { 0 };
+
+        let mut layer = Vec::with_capacity(size_u * size_v);
+        for v in 0..size_v {
+            for u in 0..size_u {
+                let (x, y, z) = plane_to_block(direction, slice, u, v);
+                layer.push(self.block_at(x, y, z).material);
+            }
+        }
+        layer
     }
-}
 
-pub fn chunk_end_of_tick_system(mut q: Query<(&mut Chunk)>) {
-    for mut c in q.iter_mut() {
-        c.updated = false;
+    // Visits every block together with its coordinates, in index order
+    pub fn iter_blocks(&self) -> impl Iterator<Item = (usize, usize, usize, &Block)> {
+        self.blocks.iter().enumerate().map(|(index, block)| {
+            let (x, y, z) = Self::coords(index);
+            (x, y, z, block)
+        })
     }
-}
 
-struct ChunkMeshData {
-    positions: Vec<[f32; 3]>,
-    normals: Vec<[f32; 3]>,
-    uvs: Vec<[f32; 2]>,
-    indices: Vec<u16>,
-}
+    // Visits the blocks that aren't air but are next to an air block of this chunk, in
+    // index order. Blocks on the edge of the chunk only count as exposed if an air block
+    // inside the chunk touches them; the neighbor chunks aren't looked at.
+    pub fn surface_blocks(&self) -> impl Iterator<Item = (usize, usize, usize, &Block)> {
+        self.iter_blocks().filter(move |(x, y, z, block)| {
+            !block.is_air() && AADirection::iter().any(|direction| {
+                adjacent_in_chunk(Self::size(), *x, *y, *z, direction)
+                    .map(|(nx, ny, nz)| self.block_at(nx, ny, nz).is_air())
+                    .unwrap_or(false)
+            })
+        })
+    }
 
-impl Default for ChunkMeshData {
-    fn default() -> Self {
-        ChunkMeshData {
-            positions: Vec::new(),
-            normals: Vec::new(),
-            uvs: Vec::new(),
-            indices: Vec::new()
+    // Like `block_at_mut`, this doesn't mark anything as changed
+    pub fn iter_blocks_mut(&mut self) -> impl Iterator<Item = (usize, usize, usize, &mut Block)> {
+        self.solid_count = None;
+        self.blocks.iter_mut().enumerate().map(|(index, block)| {
+            let (x, y, z) = Self::coords(index);
+            (x, y, z, block)
+        })
+    }
+
+    // Replaces the block at (x, y, z) and returns the previous one. The chunk is only
+    // marked as changed if the material actually differs.
+    pub fn set_block(&mut self, x : usize, y : usize, z : usize, block : Block) -> Block {
+        let changed = self.block_at(x, y, z).material != block.material;
+        let is_solid = !block.is_air();
+        let previous = std::mem::replace(&mut self.blocks[Self::index(x, y, z)], block);
+
+        if let Some(solid_count) = &mut self.solid_count {
+            let was_solid = !previous.is_air();
+            if is_solid && !was_solid {
+                *solid_count += 1;
+            } else if was_solid && !is_solid {
+                *solid_count -= 1;
+            }
+        }
+
+        if changed {
+            self.mark_block_changed(x, y, z);
         }
+
+        previous
     }
-}
 
-impl Into<Mesh> for ChunkMeshData {
+    pub fn fill(&mut self, material : MaterialID) {
+        self.fill_region(point![0, 0, 0], point![X, Y, Z], material);
+    }
 
-    fn into(self) -> Mesh {
-        let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
-        mesh.set_indices(Some(Indices::U16(self.indices)));
-        mesh.set_attribute(Mesh::ATTRIBUTE_POSITION, self.positions);
-        mesh.set_attribute(Mesh::ATTRIBUTE_NORMAL, self.normals);
-        mesh.set_attribute(Mesh::ATTRIBUTE_UV_0, self.uvs);
+    // Sets every block in the box from `min` to `max`, both inclusive. The box is clamped
+    // to the chunk.
+    pub fn fill_region(&mut self, min : Point3<usize>, max : Point3<usize>, material : MaterialID) {
+        if X == 0 || Y == 0 || Z == 0 {
+            return;
+        }
 
-        mesh
+        let max = point![max.x.min(X - 1), max.y.min(Y - 1), max.z.min(Z - 1)];
+
+        for z in min.z..=max.z {
+            for y in min.y..=max.y {
+                for x in min.x..=max.x {
+                    self.set_block(x, y, z, Block { material });
+                }
+            }
+        }
     }
-}
 
-pub trait ChunkMesher {
-    fn generate_mesh(
-        chunk: &Chunk,
-    ) -> Mesh;
-}
+    // Number of blocks that aren't air. This is O(1) unless the blocks were modified
+    // through one of the mutable accessors since the last `recount_solid`.
+    pub fn solid_count(&self) -> usize {
+        self.solid_count.unwrap_or_else(|| self.count_solid())
+    }
 
-pub fn chunk_meshing_system<Mesher: ChunkMesher>(
-    mut meshes: ResMut<Assets<Mesh>>,
-    query: Query<(&Chunk, &Handle<Mesh>)>
-) {
-    for (chunk, mesh_handle) in query.iter() {
+    pub fn recount_solid(&mut self) {
+        self.solid_count = Some(self.count_solid());
+    }
 
-        if !chunk.has_changed() {
-            continue;
+    fn count_solid(&self) -> usize {
+        self.blocks.iter().filter(|block| !block.is_air()).count()
+    }
+
+    // Blocks are fully lit until their light level is set
+    pub fn light_at(&self, x : usize, y : usize, z : usize) -> u8 {
+        match &self.light {
+            Some(light) => light[Self::index(x, y, z)],
+            None => MAX_LIGHT,
         }
+    }
 
-        let new_mesh = Mesher::generate_mesh(chunk);
+    // Sets the light level of a block, capped at `MAX_LIGHT`. Marks the block as changed
+    // if the level differs, as the faces around it are shaded by it.
+    pub fn set_light(&mut self, x : usize, y : usize, z : usize, level : u8) {
+        let index = Self::index(x, y, z);
+        let level = level.min(MAX_LIGHT);
+        let light = self.light.get_or_insert_with(|| vec![MAX_LIGHT; X * Y * Z].into_boxed_slice());
 
-        let mut mesh = meshes.get_mut(mesh_handle).unwrap();
-        *mesh = new_mesh;
+        if light[index] != level {
+            light[index] = level;
+            self.mark_block_changed(x, y, z);
+        }
     }
-}
 
-trait FaceInserter {
-    fn insert_face(
-        block_pos: Point3<usize>,
-        dimensions: Vector2<u8>,
-        mesh: &mut ChunkMeshData
-    );
-}
+    // Drops the light levels, making every block fully lit again
+    pub fn clear_light(&mut self) {
+        if self.light.take().is_some() {
+            self.set_change();
+        }
+    }
 
-struct FaceInserterImpl<const DIR: usize>;
+    pub fn count_material(&self, material : MaterialID) -> usize {
+        self.blocks.iter().filter(|block| block.material == material).count()
+    }
 
-impl FaceInserter for FaceInserterImpl<0> {
-    fn insert_face(block_pos: Point3<usize>, dimensions: Vector2<u8>, mesh: &mut ChunkMeshData) {
+    // The blocks whose material differs from the one at the same position in `other`, with
+    // their coordinates, the block in this chunk and the block in `other`
+    pub fn diff(&self, other : &Chunk<X, Y, Z>) -> Vec<(usize, usize, usize, Block, Block)> {
+        self.iter_blocks()
+            .zip(other.blocks.iter())
+            .filter(|((_, _, _, old), new)| old.material != new.material)
+            .map(|((x, y, z, old), new)| (x, y, z, old.clone(), new.clone()))
+            .collect()
+    }
 
-        let base_vertex_idx = mesh.positions.len() as u16;
-        let base_pos = point![block_pos.x as f32, block_pos.y as f32, block_pos.z as f32];
-        let u = dimensions.x as f32;
-        let v = dimensions.y as f32;
+    // Writes a list of block changes, like the ones produced by `diff`. All coordinates
+    // are checked before anything is written, so either every change is applied or none.
+    pub fn apply_changes(&mut self, changes : &[(usize, usize, usize, Block)]) -> Result<(), BlockOutOfRange> {
+        if let Some((x, y, z, _)) = changes.iter().find(|(x, y, z, _)| *x >= X || *y >= Y || *z >= Z) {
+            return Err(BlockOutOfRange { x : *x, y : *y, z : *z });
+        }
 
-        // Top left
-        mesh.positions.push([base_pos.x, base_pos.y + u, base_pos.z]);
-        mesh.normals.push([1f32, 0f32, 0f32]);
-        mesh.uvs.push([1f32, 1f32]);
+        for (x, y, z, block) in changes {
+            self.set_block(*x, *y, *z, block.clone());
+        }
+        Ok(())
+    }
 
-        // Top right
-        mesh.positions.push([base_pos.x, base_pos.y + u, base_pos.z + v]);
-        mesh.normals.push([1f32, 0f32, 0f32]);
-        mesh.uvs.push([1f32, 1f32]);
+    // Turns every block of material `old` into `new` and returns how many were replaced.
+    // Every replaced block is marked as changed.
+    pub fn replace_material(&mut self, old : MaterialID, new : MaterialID) -> usize {
+        if old == new {
+            return 0;
+        }
 
-        // Bottom right
-        mesh.positions.push([base_pos.x, base_pos.y, base_pos.z + v]);
-        mesh.normals.push([1f32, 0f32, 0f32]);
-        mesh.uvs.push([1f32, 1f32]);
+        let mut replaced = 0;
+        for index in 0..self.blocks.len() {
+            if self.blocks[index].material == old {
+                self.blocks[index].material = new;
+                let (x, y, z) = Self::coords(index);
+                self.mark_block_changed(x, y, z);
+                replaced += 1;
+            }
+        }
 
-        // Bottom left
-        mesh.positions.push([base_pos.x, base_pos.y, base_pos.z]);
-        mesh.normals.push([1f32, 0f32, 0f32]);
-        mesh.uvs.push([1f32, 1f32]);
+        if replaced > 0 && (old == AIR.id || new == AIR.id) {
+            self.recount_solid();
+        }
+        replaced
+    }
 
-        // indices
-        mesh.indices.push(base_vertex_idx);
-        mesh.indices.push(base_vertex_idx + 1);
-        mesh.indices.push(base_vertex_idx + 2);
-        mesh.indices.push(base_vertex_idx + 2);
-        mesh.indices.push(base_vertex_idx + 3);
-        mesh.indices.push(base_vertex_idx);
+    // Whether all blocks are air
+    pub fn is_empty(&self) -> bool {
+        self.solid_count() == 0
     }
-}
 
-pub struct NaiveChunkMesher {}
+    // Whether no block is air
+    pub fn is_full(&self) -> bool {
+        self.solid_count() == self.blocks.len()
+    }
 
-impl ChunkMesher for NaiveChunkMesher {
+    pub fn has_changed(&self) -> bool {
+        self.updated
+    }
 
-    fn generate_mesh(chunk: &Chunk) -> Mesh {
+    pub fn set_change(&mut self) {
+        self.updated = true;
+        self.dirty_region = Some(Self::whole_region());
+        self.revision = self.revision.wrapping_add(1);
+    }
 
-        const DIMENSIONS : Vector2<u8> = vector![1, 1];
+    // Counts the changes made through `set_change` and `mark_block_changed`. Unlike the
+    // change flag it is never reset, so it tells whether a snapshot of the chunk is stale.
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
 
-        let mut mesh = ChunkMeshData::default();
+    pub fn clear_change(&mut self) {
+        self.updated = false;
+        self.dirty_region = None;
+    }
 
-        for z in 0..Chunk::SIZE_Z {
-            for y in 0..Chunk::SIZE_Y {
-                for x in 0..Chunk::SIZE_X {
+    // Inclusive bounds of the blocks that were modified since the change flag was last
+    // cleared. `set_change` marks the whole chunk.
+    pub fn dirty_region(&self) -> Option<(Point3<usize>, Point3<usize>)> {
+        self.dirty_region
+    }
 
-                    let block = chunk.block_at(x, y, z);
-                    let block_pos = point![x, y, z];
+    fn whole_region() -> (Point3<usize>, Point3<usize>) {
+        (point![0, 0, 0], point![X.saturating_sub(1), Y.saturating_sub(1), Z.saturating_sub(1)])
+    }
 
-                    FaceInserterImpl::<0>::insert_face(
-                        point![block_pos.x + 1, block_pos.y, block_pos.z],
-                        DIMENSIONS,
-                        &mut mesh
-                    );
-                }
+    fn mark_mesh_failed(&self) {
+        self.mesh_failed.store(true, Ordering::Relaxed);
+    }
+
+    fn take_mesh_failed(&mut self) -> bool {
+        std::mem::replace(self.mesh_failed.get_mut(), false)
+    }
+
+    // Keeps the chunk changed through the current tick, until the `ChunkMeshTask` that was
+    // just started for it is inserted
+    fn mark_mesh_queued(&self) {
+        self.mesh_queued.store(true, Ordering::Relaxed);
+    }
+
+    fn take_mesh_queued(&mut self) -> bool {
+        std::mem::replace(self.mesh_queued.get_mut(), false)
+    }
+
+    // Marks the chunk as changed after the block at (x, y, z) was modified. Blocks on
+    // the outer layers are also remembered so that `chunk_neighbor_change_system` can
+    // remesh the neighbors sharing that boundary.
+    pub fn mark_block_changed(&mut self, x : usize, y : usize, z : usize) {
+        self.updated = true;
+        self.revision = self.revision.wrapping_add(1);
+        self.dirty_region = Some(match self.dirty_region {
+            Some((min, max)) => (
+                point![min.x.min(x), min.y.min(y), min.z.min(z)],
+                point![max.x.max(x), max.y.max(y), max.z.max(z)],
+            ),
+            None => (point![x, y, z], point![x, y, z]),
+        });
+
+        if x + 1 == X {
+            self.changed_edges[AADirection::XPositive as usize] = true;
+        }
+        if x == 0 {
+            self.changed_edges[AADirection::XNegative as usize] = true;
+        }
+        if y + 1 == Y {
+            self.changed_edges[AADirection::YPositive as usize] = true;
+        }
+        if y == 0 {
+            self.changed_edges[AADirection::YNegative as usize] = true;
+        }
+        if z + 1 == Z {
+            self.changed_edges[AADirection::ZPositive as usize] = true;
+        }
+        if z == 0 {
+            self.changed_edges[AADirection::ZNegative as usize] = true;
+        }
+    }
+
+    pub fn has_changed_edge(&self, direction : AADirection) -> bool {
+        self.changed_edges[direction as usize]
+    }
+
+    // Run-length encodes the blocks in index order. Each run is 8 bytes: the number of
+    // blocks in the run followed by their material id, both as little endian u32.
+    // Neighbors and change flags aren't stored.
+    pub fn serialize(&self) -> Vec<u8> {
+        let mut data = Vec::new();
+        let mut blocks = self.blocks.iter();
+
+        let mut current = match blocks.next() {
+            Some(block) => block.material,
+            None => return data,
+        };
+        let mut count = 1u32;
+
+        for block in blocks {
+            if block.material == current && count < u32::MAX {
+                count += 1;
+            } else {
+                push_run(&mut data, count, current);
+                current = block.material;
+                count = 1;
+            }
+        }
+        push_run(&mut data, count, current);
+
+        data
+    }
+
+    // Decodes a chunk written by `serialize`. The chunk comes back without neighbors
+    // and marked as changed so that it gets meshed.
+    pub fn deserialize(data : &[u8]) -> Result<Self, ChunkDecodeError> {
+        if !data.len().is_multiple_of(RUN_SIZE) {
+            return Err(ChunkDecodeError::TruncatedRun);
+        }
+
+        let mut chunk = Self::empty();
+        let mut index = 0usize;
+
+        for run in data.chunks_exact(RUN_SIZE) {
+            let count = u32::from_le_bytes([run[0], run[1], run[2], run[3]]) as usize;
+            let material = MaterialID::new(u32::from_le_bytes([run[4], run[5], run[6], run[7]]));
+
+            let end = index + count;
+            if end > chunk.blocks.len() {
+                return Err(ChunkDecodeError::TooManyBlocks);
+            }
+            for block in &mut chunk.blocks[index..end] {
+                block.material = material;
+            }
+            index = end;
+        }
+
+        if index != chunk.blocks.len() {
+            return Err(ChunkDecodeError::TooFewBlocks { expected : chunk.blocks.len(), actual : index });
+        }
+        chunk.recount_solid();
+
+        Ok(chunk)
+    }
+}
+
+const RUN_SIZE : usize = 8;
+
+fn push_run(data : &mut Vec<u8>, count : u32, material : MaterialID) {
+    data.extend_from_slice(&count.to_le_bytes());
+    data.extend_from_slice(&material.id.to_le_bytes());
+}
+
+#[derive(Eq, PartialEq, Debug, Copy, Clone)]
+pub enum ChunkDecodeError {
+    // The data ends in the middle of a run
+    TruncatedRun,
+    // The runs add up to more blocks than fit into the chunk
+    TooManyBlocks,
+    // The runs don't cover the whole chunk
+    TooFewBlocks { expected : usize, actual : usize },
+}
+
+impl std::fmt::Display for ChunkDecodeError {
+    fn fmt(&self, f : &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ChunkDecodeError::TruncatedRun => write!(f, "chunk data ends in the middle of a run"),
+            ChunkDecodeError::TooManyBlocks => write!(f, "chunk data contains more blocks than fit into the chunk"),
+            ChunkDecodeError::TooFewBlocks { expected, actual } => {
+                write!(f, "chunk data contains {} blocks, expected {}", actual, expected)
+            },
+        }
+    }
+}
+
+impl std::error::Error for ChunkDecodeError {}
+
+#[derive(Eq, PartialEq, Debug, Copy, Clone)]
+pub struct BlockOutOfRange {
+    pub x : usize,
+    pub y : usize,
+    pub z : usize,
+}
+
+impl std::fmt::Display for BlockOutOfRange {
+    fn fmt(&self, f : &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "block ({}, {}, {}) is outside of the chunk", self.x, self.y, self.z)
+    }
+}
+
+impl std::error::Error for BlockOutOfRange {}
+
+// Builds a chunk by chaining block edits, for generators and test setups. The chunk starts
+// out as air and comes out marked as changed as a whole, like `Chunk::empty`.
+pub struct ChunkBuilder<const X: usize = 16, const Y: usize = 16, const Z: usize = 16> {
+    chunk : Chunk<X, Y, Z>,
+}
+
+impl<const X: usize, const Y: usize, const Z: usize> Default for ChunkBuilder<X, Y, Z> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const X: usize, const Y: usize, const Z: usize> ChunkBuilder<X, Y, Z> {
+
+    pub fn new() -> Self {
+        ChunkBuilder {
+            chunk : Chunk::empty(),
+        }
+    }
+
+    pub fn set(mut self, x : usize, y : usize, z : usize, material : MaterialID) -> Self {
+        self.chunk.set_block(x, y, z, Block { material });
+        self
+    }
+
+    pub fn fill(mut self, material : MaterialID) -> Self {
+        self.chunk.fill(material);
+        self
+    }
+
+    // See `Chunk::fill_region`, both corners are inclusive
+    pub fn fill_region(mut self, min : Point3<usize>, max : Point3<usize>, material : MaterialID) -> Self {
+        self.chunk.fill_region(min, max, material);
+        self
+    }
+
+    pub fn build(self) -> Chunk<X, Y, Z> {
+        self.chunk
+    }
+}
+
+// Read access to the blocks of a chunk, which is all that the meshers need. Lets them
+// mesh other storages than the dense `Chunk`, like `SparseChunk`.
+pub trait BlockStorage<const X: usize, const Y: usize, const Z: usize> {
+
+    // Panics if the coordinates are outside of the chunk
+    fn block_at(&self, x : usize, y : usize, z : usize) -> Block;
+
+    // Storages without light levels are fully lit
+    fn light_at(&self, _x : usize, _y : usize, _z : usize) -> u8 {
+        MAX_LIGHT
+    }
+
+    fn size(&self) -> Vector3<usize> {
+        vector![X, Y, Z]
+    }
+
+    // The entity of the neighboring chunk, for storages that are linked to their neighbors
+    fn get_neighbor(&self, _direction : AADirection) -> Option<Entity> {
+        None
+    }
+}
+
+impl<const X: usize, const Y: usize, const Z: usize> BlockStorage<X, Y, Z> for Chunk<X, Y, Z> {
+
+    fn block_at(&self, x : usize, y : usize, z : usize) -> Block {
+        Chunk::block_at(self, x, y, z).clone()
+    }
+
+    fn light_at(&self, x : usize, y : usize, z : usize) -> u8 {
+        Chunk::light_at(self, x, y, z)
+    }
+
+    fn get_neighbor(&self, direction : AADirection) -> Option<Entity> {
+        Chunk::get_neighbor(self, direction)
+    }
+}
+
+// Block storage for chunks that are almost entirely air, like the ones in the sky. Only
+// the blocks that aren't air are kept, by their index in the chunk.
+#[derive(Clone, Debug)]
+pub struct SparseChunk<const X: usize = 16, const Y: usize = 16, const Z: usize = 16> {
+    blocks : HashMap<usize, Block>,
+}
+
+impl<const X: usize, const Y: usize, const Z: usize> Default for SparseChunk<X, Y, Z> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const X: usize, const Y: usize, const Z: usize> SparseChunk<X, Y, Z> {
+
+    // All blocks are air
+    pub fn new() -> Self {
+        SparseChunk {
+            blocks : HashMap::new(),
+        }
+    }
+
+    pub fn from_chunk(chunk : &Chunk<X, Y, Z>) -> Self {
+        let blocks = chunk.blocks.iter()
+            .enumerate()
+            .filter(|(_, block)| !block.is_air())
+            .map(|(index, block)| (index, block.clone()))
+            .collect();
+
+        SparseChunk { blocks }
+    }
+
+    // The chunk comes back without neighbors and marked as changed
+    pub fn to_chunk(&self) -> Chunk<X, Y, Z> {
+        let mut chunk = Chunk::empty();
+        for (index, block) in self.blocks.iter() {
+            chunk.blocks[*index] = block.clone();
+        }
+        chunk.recount_solid();
+        chunk
+    }
+
+    pub fn block_at(&self, x : usize, y : usize, z : usize) -> Block {
+        self.try_block_at(x, y, z)
+            .expect("Block index out of range")
+    }
+
+    pub fn try_block_at(&self, x : usize, y : usize, z : usize) -> Option<Block> {
+        if x >= X || y >= Y || z >= Z {
+            return None;
+        }
+        Some(self.blocks.get(&Chunk::<X, Y, Z>::index(x, y, z)).cloned().unwrap_or_default())
+    }
+
+    // Replaces the block at (x, y, z) and returns the previous one. Air isn't stored.
+    pub fn set_block(&mut self, x : usize, y : usize, z : usize, block : Block) -> Block {
+        assert!(x < X && y < Y && z < Z, "Block index out of range");
+
+        let index = Chunk::<X, Y, Z>::index(x, y, z);
+        let previous = if block.is_air() {
+            self.blocks.remove(&index)
+        } else {
+            self.blocks.insert(index, block)
+        };
+        previous.unwrap_or_default()
+    }
+
+    // Number of blocks that aren't air
+    pub fn solid_count(&self) -> usize {
+        self.blocks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.blocks.is_empty()
+    }
+}
+
+impl<const X: usize, const Y: usize, const Z: usize> BlockStorage<X, Y, Z> for SparseChunk<X, Y, Z> {
+
+    fn block_at(&self, x : usize, y : usize, z : usize) -> Block {
+        SparseChunk::block_at(self, x, y, z)
+    }
+}
+
+// Block storage that keeps each material once in a palette and packs the palette index of
+// every block into as few bits as the palette needs. Chunks that only use a handful of
+// materials take a fraction of the memory of a `Chunk`, at the cost of unpacking blocks
+// on access. Blocks are laid out in the same order as in `Chunk`.
+#[derive(Clone, Debug)]
+pub struct PalettedBlocks<const X: usize = 16, const Y: usize = 16, const Z: usize = 16> {
+    palette : Vec<MaterialID>,
+    // Number of blocks using each palette entry
+    counts : Vec<usize>,
+    // Bits per palette index, 0 while the palette has a single entry
+    bits : u32,
+    // Indices never straddle two words
+    words : Vec<u64>,
+}
+
+impl<const X: usize, const Y: usize, const Z: usize> Default for PalettedBlocks<X, Y, Z> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<const X: usize, const Y: usize, const Z: usize> PalettedBlocks<X, Y, Z> {
+
+    // All blocks are air
+    pub fn new() -> Self {
+        PalettedBlocks {
+            palette : vec![AIR.id],
+            counts : vec![X * Y * Z],
+            bits : 0,
+            words : Vec::new(),
+        }
+    }
+
+    pub fn from_chunk(chunk : &Chunk<X, Y, Z>) -> Self {
+        let mut palette : Vec<MaterialID> = Vec::new();
+        let mut counts = Vec::new();
+        let mut indices = Vec::with_capacity(X * Y * Z);
+
+        for block in chunk.blocks.iter() {
+            let index = match palette.iter().position(|material| *material == block.material) {
+                Some(index) => index,
+                None => {
+                    palette.push(block.material);
+                    counts.push(0);
+                    palette.len() - 1
+                },
+            };
+            counts[index] += 1;
+            indices.push(index as u64);
+        }
+
+        if palette.is_empty() {
+            palette.push(AIR.id);
+            counts.push(0);
+        }
+
+        let mut blocks = PalettedBlocks {
+            bits : Self::bits_for(palette.len()),
+            palette,
+            counts,
+            words : Vec::new(),
+        };
+        blocks.pack(&indices);
+        blocks
+    }
+
+    // The chunk comes back without neighbors and marked as changed
+    pub fn to_chunk(&self) -> Chunk<X, Y, Z> {
+        let mut chunk = Chunk::empty();
+        for (index, block) in chunk.blocks.iter_mut().enumerate() {
+            block.material = self.palette[self.palette_index(index)];
+        }
+        chunk.recount_solid();
+        chunk
+    }
+
+    // Materials can appear more than once after `replace_material`
+    pub fn palette(&self) -> &[MaterialID] {
+        &self.palette
+    }
+
+    pub fn bits_per_block(&self) -> u32 {
+        self.bits
+    }
+
+    pub fn count_material(&self, material : MaterialID) -> usize {
+        self.palette.iter()
+            .zip(self.counts.iter())
+            .filter(|(entry, _)| **entry == material)
+            .map(|(_, count)| count)
+            .sum()
+    }
+
+    // Turns every block of material `old` into `new` and returns how many were replaced.
+    // Only the palette entries are renamed, without touching the blocks. If `new` is in the
+    // palette already it stays in there twice until the blocks are rebuilt with `from_chunk`.
+    pub fn replace_material(&mut self, old : MaterialID, new : MaterialID) -> usize {
+        if old == new {
+            return 0;
+        }
+
+        let mut replaced = 0;
+        for (entry, count) in self.palette.iter_mut().zip(self.counts.iter()) {
+            if *entry == old {
+                *entry = new;
+                replaced += count;
+            }
+        }
+        replaced
+    }
+
+    // Heap memory used by the palette and the packed indices, in bytes
+    pub fn memory_usage(&self) -> usize {
+        self.palette.capacity() * std::mem::size_of::<MaterialID>()
+            + self.counts.capacity() * std::mem::size_of::<usize>()
+            + self.words.capacity() * std::mem::size_of::<u64>()
+    }
+
+    pub fn block_at(&self, x : usize, y : usize, z : usize) -> Block {
+        self.try_block_at(x, y, z)
+            .expect("Block index out of range")
+    }
+
+    pub fn try_block_at(&self, x : usize, y : usize, z : usize) -> Option<Block> {
+        if x >= X || y >= Y || z >= Z {
+            return None;
+        }
+        let material = self.palette[self.palette_index(Chunk::<X, Y, Z>::index(x, y, z))];
+        Some(Block { material })
+    }
+
+    // Replaces the block at (x, y, z) and returns the previous one. New materials are
+    // added to the palette, widening the indices if necessary. Materials that are no
+    // longer used stay in the palette until the blocks are rebuilt with `from_chunk`.
+    pub fn set_block(&mut self, x : usize, y : usize, z : usize, block : Block) -> Block {
+        let index = Chunk::<X, Y, Z>::index(x, y, z);
+        let previous = self.block_at(x, y, z);
+        let previous_index = self.palette_index(index);
+
+        let palette_index = match self.palette.iter().position(|material| *material == block.material) {
+            Some(palette_index) => palette_index,
+            None => {
+                self.palette.push(block.material);
+                self.counts.push(0);
+                let bits = Self::bits_for(self.palette.len());
+                if bits != self.bits {
+                    self.repack(bits);
+                }
+                self.palette.len() - 1
+            },
+        };
+
+        if self.bits > 0 {
+            let (word, shift) = self.location(index);
+            let mask = ((1u64 << self.bits) - 1) << shift;
+            self.words[word] = (self.words[word] & !mask) | ((palette_index as u64) << shift);
+        }
+        self.counts[previous_index] -= 1;
+        self.counts[palette_index] += 1;
+
+        previous
+    }
+
+    fn bits_for(palette_size : usize) -> u32 {
+        if palette_size <= 1 {
+            0
+        } else {
+            usize::BITS - (palette_size - 1).leading_zeros()
+        }
+    }
+
+    fn per_word(bits : u32) -> usize {
+        (u64::BITS / bits) as usize
+    }
+
+    fn location(&self, index : usize) -> (usize, u32) {
+        let per_word = Self::per_word(self.bits);
+        (index / per_word, (index % per_word) as u32 * self.bits)
+    }
+
+    fn palette_index(&self, index : usize) -> usize {
+        if self.bits == 0 {
+            return 0;
+        }
+        let (word, shift) = self.location(index);
+        ((self.words[word] >> shift) & ((1u64 << self.bits) - 1)) as usize
+    }
+
+    fn repack(&mut self, bits : u32) {
+        let indices : Vec<u64> = (0..X * Y * Z).map(|index| self.palette_index(index) as u64).collect();
+        self.bits = bits;
+        self.pack(&indices);
+    }
+
+    fn pack(&mut self, indices : &[u64]) {
+        self.words.clear();
+        if self.bits == 0 {
+            self.words.shrink_to_fit();
+            return;
+        }
+
+        let per_word = Self::per_word(self.bits);
+        self.words = indices.chunks(per_word)
+            .map(|chunk| {
+                chunk.iter()
+                    .enumerate()
+                    .fold(0u64, |word, (slot, index)| word | (index << (slot as u32 * self.bits)))
+            })
+            .collect();
+    }
+}
+
+impl<const X: usize, const Y: usize, const Z: usize> BlockStorage<X, Y, Z> for PalettedBlocks<X, Y, Z> {
+
+    fn block_at(&self, x : usize, y : usize, z : usize) -> Block {
+        PalettedBlocks::block_at(self, x, y, z)
+    }
+}
+
+// Recomputes the light levels of the chunk from emitters given as (x, y, z, level). Light
+// spreads to the six adjacent blocks, losing one level per step, through every block that
+// isn't opaque. Blocks no light reaches end up at 0. Light from neighboring chunks isn't
+// taken into account. Only blocks whose light level changed are marked as changed, so
+// neighbors are only remeshed if the light along their edge changed.
+pub fn propagate_light<const X: usize, const Y: usize, const Z: usize>(
+    chunk: &mut Chunk<X, Y, Z>,
+    registry: &MaterialRegistry,
+    emitters: &[(usize, usize, usize, u8)],
+) {
+    let mut light = vec![0u8; X * Y * Z].into_boxed_slice();
+    let mut queue = VecDeque::new();
+
+    for &(x, y, z, level) in emitters {
+        if x >= X || y >= Y || z >= Z {
+            continue;
+        }
+        let index = Chunk::<X, Y, Z>::index(x, y, z);
+        let level = level.min(MAX_LIGHT);
+        if level > light[index] {
+            light[index] = level;
+            queue.push_back((x, y, z));
+        }
+    }
+
+    while let Some((x, y, z)) = queue.pop_front() {
+        let level = light[Chunk::<X, Y, Z>::index(x, y, z)];
+        if level <= 1 {
+            continue;
+        }
+
+        for direction in AADirection::iter() {
+            let (dx, dy, dz) = direction.offset();
+            let (nx, ny, nz) = (x as i64 + dx as i64, y as i64 + dy as i64, z as i64 + dz as i64);
+            if nx < 0 || ny < 0 || nz < 0 || nx >= X as i64 || ny >= Y as i64 || nz >= Z as i64 {
+                continue;
+            }
+            let (nx, ny, nz) = (nx as usize, ny as usize, nz as usize);
+
+            let index = Chunk::<X, Y, Z>::index(nx, ny, nz);
+            if light[index] >= level - 1 || chunk.block_at(nx, ny, nz).is_opaque(registry) {
+                continue;
+            }
+            light[index] = level - 1;
+            queue.push_back((nx, ny, nz));
+        }
+    }
+
+    let previous = chunk.light.take();
+    for (index, level) in light.iter().enumerate() {
+        let before = previous.as_ref().map(|previous| previous[index]).unwrap_or(MAX_LIGHT);
+        if before != *level {
+            let (x, y, z) = Chunk::<X, Y, Z>::coords(index);
+            chunk.mark_block_changed(x, y, z);
+        }
+    }
+    chunk.light = Some(light);
+}
+
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub struct RaycastHit {
+    pub block : Point3<usize>,
+    // The face of `block` that the ray entered through. The block adjacent to it in
+    // this direction is where a new block would be placed.
+    pub face : AADirection,
+    // Distance along the ray from the origin to the hit
+    pub distance : f32,
+}
+
+// Walks the blocks along the ray in chunk local coordinates and returns the first block
+// that isn't air, within `max_dist` of `origin`. The origin may lie outside of the chunk.
+// A ray starting inside a block hits it right away, on the face pointing back along the ray.
+pub fn raycast<const X: usize, const Y: usize, const Z: usize>(
+    chunk: &Chunk<X, Y, Z>,
+    origin: Point3<f32>,
+    dir: Vector3<f32>,
+    max_dist: f32,
+) -> Option<RaycastHit> {
+    let length = dir.norm();
+    if length == 0f32 || !length.is_finite() {
+        return None;
+    }
+    let dir = dir / length;
+
+    let size = [X as i64, Y as i64, Z as i64];
+
+    let mut cell = [0i64; 3];
+    let mut step = [0i64; 3];
+    // Distance along the ray to the next block boundary on each axis
+    let mut next = [f32::INFINITY; 3];
+    // Distance along the ray between two block boundaries on each axis
+    let mut delta = [f32::INFINITY; 3];
+
+    for axis in 0..3 {
+        cell[axis] = origin[axis].floor() as i64;
+        if dir[axis] > 0f32 {
+            step[axis] = 1;
+            delta[axis] = 1f32 / dir[axis];
+            next[axis] = (cell[axis] as f32 + 1f32 - origin[axis]) * delta[axis];
+        } else if dir[axis] < 0f32 {
+            step[axis] = -1;
+            delta[axis] = -1f32 / dir[axis];
+            next[axis] = (origin[axis] - cell[axis] as f32) * delta[axis];
+        }
+    }
+
+    // Until the first step, report the face the ray points away from
+    let dominant = (0..3)
+        .max_by(|a, b| dir[*a].abs().partial_cmp(&dir[*b].abs()).unwrap())
+        .unwrap();
+    let mut face = AADirection::from_axis_sign(Axis::ALL[dominant], step[dominant] <= 0);
+    let mut distance = 0f32;
+
+    while distance <= max_dist {
+        let inside = (0..3).all(|axis| cell[axis] >= 0 && cell[axis] < size[axis]);
+        if inside {
+            let block = point![cell[0] as usize, cell[1] as usize, cell[2] as usize];
+            if !chunk.block_at(block.x, block.y, block.z).is_air() {
+                return Some(RaycastHit { block, face, distance });
+            }
+        } else {
+            // Stop once the ray is moving away from the chunk on an axis it is outside of
+            let leaving = (0..3).any(|axis| {
+                (cell[axis] < 0 && step[axis] <= 0) || (cell[axis] >= size[axis] && step[axis] >= 0)
+            });
+            if leaving {
+                return None;
+            }
+        }
+
+        let axis = (0..3)
+            .min_by(|a, b| next[*a].partial_cmp(&next[*b]).unwrap())
+            .unwrap();
+        distance = next[axis];
+        next[axis] += delta[axis];
+        cell[axis] += step[axis];
+        face = AADirection::from_axis_sign(Axis::ALL[axis], step[axis] <= 0);
+    }
+
+    None
+}
+
+// Links chunk `b` as the neighbor of chunk `a` in `direction` and `a` as the neighbor of
+// `b` in the opposite direction. Both chunks are marked as changed, as their boundary
+// faces depend on their neighbors. Returns false without linking anything if either
+// entity has no chunk.
+pub fn link_chunks<const X: usize, const Y: usize, const Z: usize>(
+    world: &mut World,
+    a: Entity,
+    b: Entity,
+    direction: AADirection
+) -> bool {
+    if world.get::<Chunk<X, Y, Z>>(a).is_none() || world.get::<Chunk<X, Y, Z>>(b).is_none() {
+        return false;
+    }
+
+    let mut chunk_a = world.get_mut::<Chunk<X, Y, Z>>(a).unwrap();
+    chunk_a.set_neighbor(b, direction);
+    chunk_a.set_change();
+
+    let mut chunk_b = world.get_mut::<Chunk<X, Y, Z>>(b).unwrap();
+    chunk_b.set_neighbor(a, direction.opposite());
+    chunk_b.set_change();
+
+    true
+}
+
+// Marks the neighbors of chunks whose boundary blocks changed as changed themselves.
+// Has to run after blocks are modified and before the meshing system.
+pub fn chunk_neighbor_change_system<const X: usize, const Y: usize, const Z: usize>(
+    mut changed_neighbors: Local<HashSet<Entity>>,
+    mut q: Query<&mut Chunk<X, Y, Z>>
+) {
+
+    for mut c in q.iter_mut() {
+
+        if !c.changed_edges.iter().any(|changed| *changed) {
+            continue;
+        }
+
+        for (direction, neighbor) in c.iter_neighbors() {
+            if c.changed_edges[direction as usize] {
+                changed_neighbors.insert(neighbor);
+            }
+        }
+
+        c.changed_edges = [false; 6];
+    }
+
+    // A chunk next to several changed edges is only marked once
+    for neighbor in changed_neighbors.drain() {
+        if let Ok(mut c) = q.get_mut(neighbor) {
+            c.set_change();
+        }
+    }
+}
+
+type ChunkMeshStatus<'a, const X: usize, const Y: usize, const Z: usize> = (
+    &'a mut Chunk<X, Y, Z>,
+    Option<&'a Handle<Mesh>>,
+    Option<&'a TransparentChunkMesh>,
+    Option<&'a ChunkMeshTask>,
+);
+
+// Chunks whose mesh assets aren't available yet stay changed, so that they are meshed
+// once the assets exist.
+pub fn chunk_end_of_tick_system<const X: usize, const Y: usize, const Z: usize>(
+    meshes: Res<Assets<Mesh>>,
+    mut q: Query<ChunkMeshStatus<X, Y, Z>>
+) {
+    for (mut c, mesh_handle, transparent_mesh, mesh_task) in q.iter_mut() {
+        // Only dereference mutably when there is something to clear, so that clean chunks
+        // don't show up in bevy's change detection
+        if !c.has_changed() {
+            continue;
+        }
+
+        // Chunks meshed in the background are cleared once their mesh is applied. The task
+        // of a chunk queued in this tick is only inserted at the end of the stage.
+        if c.take_mesh_queued() || mesh_task.is_some() {
+            continue;
+        }
+
+        let pending = mesh_handle
+            .map(|mesh_handle| !mesh_assets_available(&meshes, mesh_handle, transparent_mesh))
+            .unwrap_or(false);
+
+        // Chunks that failed to mesh are retried next tick
+        if !pending && !c.take_mesh_failed() {
+            c.clear_change();
+        }
+    }
+}
+
+#[derive(Default, Clone)]
+pub struct ChunkMeshData {
+    positions: Vec<[f32; 3]>,
+    normals: Vec<[f32; 3]>,
+    uvs: Vec<[f32; 2]>,
+    colors: Vec<[f32; 4]>,
+    // Only filled if tangents are enabled in the `MeshConfig`
+    tangents: Vec<[f32; 4]>,
+    // Material of each vertex, not passed on to the mesh
+    materials: Vec<MaterialID>,
+    indices: Vec<u32>,
+}
+
+impl ChunkMeshData {
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // Empties all buffers but keeps their allocations for the next meshing pass
+    pub fn clear(&mut self) {
+        self.positions.clear();
+        self.normals.clear();
+        self.uvs.clear();
+        self.colors.clear();
+        self.tangents.clear();
+        self.materials.clear();
+        self.indices.clear();
+    }
+
+    // Merges vertices that are identical in all attributes and points the indices at the
+    // merged vertices. Vertices of neighboring faces only match where their UVs do. The
+    // vertices are compacted in place, so the buffers keep their allocations.
+    fn weld_vertices(&mut self) {
+        let mut remap = Vec::with_capacity(self.positions.len());
+        let mut lookup = HashMap::new();
+        let mut welded = 0;
+
+        for i in 0..self.positions.len() {
+            let key = (
+                self.positions[i].map(f32::to_bits),
+                self.normals[i].map(f32::to_bits),
+                self.uvs[i].map(f32::to_bits),
+                self.colors[i].map(f32::to_bits),
+                self.materials[i],
+            );
+
+            // Welded vertices never move back past one that hasn't been looked at yet
+            let index = match lookup.entry(key) {
+                Entry::Occupied(entry) => *entry.get(),
+                Entry::Vacant(entry) => {
+                    self.positions[welded] = self.positions[i];
+                    self.normals[welded] = self.normals[i];
+                    self.uvs[welded] = self.uvs[i];
+                    self.colors[welded] = self.colors[i];
+                    if !self.tangents.is_empty() {
+                        self.tangents[welded] = self.tangents[i];
+                    }
+                    self.materials[welded] = self.materials[i];
+                    welded += 1;
+                    *entry.insert(welded as u32 - 1)
+                },
+            };
+            remap.push(index);
+        }
+
+        self.positions.truncate(welded);
+        self.normals.truncate(welded);
+        self.uvs.truncate(welded);
+        self.colors.truncate(welded);
+        self.tangents.truncate(welded);
+        self.materials.truncate(welded);
+        for index in self.indices.iter_mut() {
+            *index = remap[*index as usize];
+        }
+    }
+
+    // Gives every vertex of an axis aligned face the UV of its position in the plane of the
+    // face, measured from the chunk's corner the face's u and v axes start at. The corners
+    // of neighboring faces then share their UVs. `size` is the size of the chunk.
+    fn align_face_uvs(&mut self, atlas: &MaterialAtlas, size: Vector3<usize>) {
+        let size = size.map(|extent| extent as f32);
+
+        for i in 0..self.positions.len() {
+            let normal = self.normals[i];
+            let direction = match normal.iter().position(|n| n.abs() == 1f32) {
+                Some(axis) => AADirection::from_axis_sign(Axis::ALL[axis], normal[axis] > 0f32),
+                None => continue,
+            };
+
+            let [x, y, z] = self.positions[i];
+            let uv = match direction {
+                AADirection::XPositive => [size.z - z, size.y - y],
+                AADirection::XNegative => [z, size.y - y],
+                AADirection::YPositive => [x, z],
+                AADirection::YNegative => [size.x - x, z],
+                AADirection::ZPositive => [x, size.y - y],
+                AADirection::ZNegative => [size.x - x, size.y - y],
+            };
+            self.uvs[i] = atlas.rect(self.materials[i], direction).map(uv);
+        }
+    }
+
+    // Generates a tangent for every vertex that points along the u axis of the face
+    // implied by the vertex normal. Normals that aren't axis aligned, from smoothing or
+    // models, get that tangent made orthogonal to them.
+    fn generate_tangents(&mut self) {
+        self.tangents.clear();
+
+        for normal in self.normals.iter() {
+            let axis = (0..3)
+                .max_by(|a, b| normal[*a].abs().partial_cmp(&normal[*b].abs()).unwrap())
+                .unwrap();
+            let direction = AADirection::from_axis_sign(Axis::ALL[axis], normal[axis] >= 0f32);
+
+            let tangent = face_tangent(direction);
+            let dot = tangent[0] * normal[0] + tangent[1] * normal[1] + tangent[2] * normal[2];
+            let tangent = [
+                tangent[0] - normal[0] * dot,
+                tangent[1] - normal[1] * dot,
+                tangent[2] - normal[2] * dot,
+            ];
+            let length = (tangent[0] * tangent[0] + tangent[1] * tangent[1] + tangent[2] * tangent[2]).sqrt();
+
+            // The v axis of every face runs opposite to normal x tangent
+            self.tangents.push([tangent[0] / length, tangent[1] / length, tangent[2] / length, -1f32]);
+        }
+    }
+
+    fn translate(&mut self, offset: Vector3<f32>) {
+        if offset == Vector3::zeros() {
+            return;
+        }
+        for position in self.positions.iter_mut() {
+            position[0] += offset.x;
+            position[1] += offset.y;
+            position[2] += offset.z;
+        }
+    }
+
+    // Appends the vertices of `other` moved by `offset` and rebases its indices onto them.
+    // Both have to either have tangents or not, unless `self` is empty.
+    fn append(&mut self, other: &ChunkMeshData, offset: Vector3<f32>) {
+        let base = self.positions.len() as u32;
+
+        self.positions.extend(other.positions.iter().map(|position| {
+            [position[0] + offset.x, position[1] + offset.y, position[2] + offset.z]
+        }));
+        self.normals.extend_from_slice(&other.normals);
+        self.uvs.extend_from_slice(&other.uvs);
+        self.colors.extend_from_slice(&other.colors);
+        self.tangents.extend_from_slice(&other.tangents);
+        self.materials.extend_from_slice(&other.materials);
+        self.indices.extend(other.indices.iter().map(|index| index + base));
+    }
+
+    // Keeps the quads for which `keep` returns true, by their index. Only valid while every
+    // quad is four consecutive vertices and six consecutive indices, as emitted by
+    // `insert_quad`.
+    fn retain_quads(&mut self, keep: impl Fn(usize) -> bool) {
+        let mut kept = 0;
+
+        for quad in 0..self.vertex_count() / 4 {
+            if !keep(quad) {
+                continue;
+            }
+
+            if kept != quad {
+                for corner in 0..4 {
+                    let (from, to) = (quad * 4 + corner, kept * 4 + corner);
+                    self.positions[to] = self.positions[from];
+                    self.normals[to] = self.normals[from];
+                    self.uvs[to] = self.uvs[from];
+                    self.colors[to] = self.colors[from];
+                    if !self.tangents.is_empty() {
+                        self.tangents[to] = self.tangents[from];
+                    }
+                    self.materials[to] = self.materials[from];
+                }
+                for corner in 0..6 {
+                    let index = self.indices[quad * 6 + corner];
+                    self.indices[kept * 6 + corner] = index - (quad * 4) as u32 + (kept * 4) as u32;
+                }
+            }
+            kept += 1;
+        }
+
+        self.positions.truncate(kept * 4);
+        self.normals.truncate(kept * 4);
+        self.uvs.truncate(kept * 4);
+        self.colors.truncate(kept * 4);
+        self.tangents.truncate(kept * 4);
+        self.materials.truncate(kept * 4);
+        self.indices.truncate(kept * 6);
+    }
+
+    // Swaps the last two vertices of every triangle
+    fn flip_winding(&mut self) {
+        for triangle in self.indices.chunks_exact_mut(3) {
+            triangle.swap(1, 2);
+        }
+    }
+
+    // Replaces the normal of every vertex with the average normal of all vertices of the
+    // same material at its position. Vertices aren't merged, so UV seams are kept.
+    fn smooth_normals(&mut self) {
+        let mut sums : HashMap<([u32; 3], MaterialID), [f32; 3]> = HashMap::new();
+        let keys = self.positions.iter().zip(self.materials.iter()).map(|(position, material)| {
+            ([position[0].to_bits(), position[1].to_bits(), position[2].to_bits()], *material)
+        });
+
+        for (key, normal) in keys.clone().zip(self.normals.iter()) {
+            let sum = sums.entry(key).or_insert([0f32; 3]);
+            for axis in 0..3 {
+                sum[axis] += normal[axis];
+            }
+        }
+
+        for (key, normal) in keys.zip(self.normals.iter_mut()) {
+            let sum = sums[&key];
+            let length = (sum[0] * sum[0] + sum[1] * sum[1] + sum[2] * sum[2]).sqrt();
+            if length > 0f32 {
+                *normal = [sum[0] / length, sum[1] / length, sum[2] / length];
+            }
+        }
+    }
+
+    pub fn vertex_count(&self) -> usize {
+        self.positions.len()
+    }
+
+    pub fn vertex_capacity(&self) -> usize {
+        self.positions.capacity()
+    }
+
+    // Takes the vertex and index buffers of a mesh that is no longer needed, e.g. one that
+    // was just replaced by a newly generated mesh, to reuse their allocations. Buffers are
+    // only swapped in if they are larger than the ones already there.
+    pub fn reclaim(&mut self, mut mesh : Mesh) {
+        if let Some(VertexAttributeValues::Float3(positions)) = mesh.attribute_mut(Mesh::ATTRIBUTE_POSITION) {
+            reclaim_buffer(&mut self.positions, positions);
+        }
+        if let Some(VertexAttributeValues::Float3(normals)) = mesh.attribute_mut(Mesh::ATTRIBUTE_NORMAL) {
+            reclaim_buffer(&mut self.normals, normals);
+        }
+        if let Some(VertexAttributeValues::Float2(uvs)) = mesh.attribute_mut(Mesh::ATTRIBUTE_UV_0) {
+            reclaim_buffer(&mut self.uvs, uvs);
+        }
+        if let Some(VertexAttributeValues::Float4(colors)) = mesh.attribute_mut(Mesh::ATTRIBUTE_COLOR) {
+            reclaim_buffer(&mut self.colors, colors);
+        }
+        // 16 bit indices can't be reused for the u32 indices of the buffers
+        if let Some(Indices::U32(indices)) = mesh.indices_mut() {
+            reclaim_buffer(&mut self.indices, indices);
+        }
+    }
+}
+
+fn reclaim_buffer<T>(buffer : &mut Vec<T>, replaced : &mut Vec<T>) {
+    if replaced.capacity() > buffer.capacity() {
+        replaced.clear();
+        std::mem::swap(buffer, replaced);
+    }
+}
+
+// Scratch buffers that are reused across meshing passes to avoid reallocating them
+// for every chunk, see `generate_mesh_with_buffers`
+#[derive(Default, Clone)]
+pub struct ChunkMeshBuffers {
+    pub opaque : ChunkMeshData,
+    pub transparent : ChunkMeshData,
+}
+
+impl ChunkMeshBuffers {
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn clear(&mut self) {
+        self.opaque.clear();
+        self.transparent.clear();
+    }
+
+    // See `ChunkMeshData::reclaim`
+    pub fn reclaim(&mut self, opaque : Option<Mesh>, transparent : Option<Mesh>) {
+        if let Some(opaque) = opaque {
+            self.opaque.reclaim(opaque);
+        }
+        if let Some(transparent) = transparent {
+            self.transparent.reclaim(transparent);
+        }
+    }
+}
+
+// Falls back to 32 bit indices only once the vertices don't fit into 16 bits
+fn compact_indices(vertex_count: usize, indices: Vec<u32>) -> Indices {
+    if vertex_count <= u16::MAX as usize + 1 {
+        Indices::U16(indices.into_iter().map(|index| index as u16).collect())
+    } else {
+        Indices::U32(indices)
+    }
+}
+
+// Strings the triangles together one by one, separated by degenerate triangles. Every
+// triangle starts at an even position so that the strip keeps its winding.
+fn triangle_strip(indices: &[u32]) -> Vec<u32> {
+    let mut strip = Vec::with_capacity(indices.len() * 2);
+
+    for triangle in indices.chunks_exact(3) {
+        if let Some(&last) = strip.last() {
+            strip.push(last);
+            strip.push(triangle[0]);
+            if strip.len() % 2 == 1 {
+                strip.push(triangle[0]);
+            }
+        }
+        strip.extend_from_slice(triangle);
+    }
+
+    strip
+}
+
+// Custom vertex attribute with the `MaterialID` of each vertex's face, for shaders that look
+// up the texture by material instead of using the atlas UVs
+pub const ATTRIBUTE_MATERIAL_ID : &str = "Vertex_MaterialId";
+
+// How the vertices of a generated mesh are assembled into primitives
+#[derive(Eq, PartialEq, Debug, Copy, Clone)]
+pub enum MeshTopology {
+    TriangleList,
+    // Uses degenerate triangles between the triangles of the list
+    TriangleStrip,
+    // Vertices only, without indices
+    PointList,
+}
+
+impl ChunkMeshData {
+
+    pub fn into_mesh(self, topology : MeshTopology) -> Mesh {
+        let vertex_count = self.positions.len();
+        let (mut mesh, indices) = match topology {
+            MeshTopology::TriangleList => (Mesh::new(PrimitiveTopology::TriangleList), Some(self.indices)),
+            MeshTopology::TriangleStrip => (Mesh::new(PrimitiveTopology::TriangleStrip), Some(triangle_strip(&self.indices))),
+            MeshTopology::PointList => (Mesh::new(PrimitiveTopology::PointList), None),
+        };
+
+        mesh.set_indices(indices.map(|indices| compact_indices(vertex_count, indices)));
+        mesh.set_attribute(Mesh::ATTRIBUTE_POSITION, self.positions);
+        mesh.set_attribute(Mesh::ATTRIBUTE_NORMAL, self.normals);
+        mesh.set_attribute(Mesh::ATTRIBUTE_UV_0, self.uvs);
+
+        if !self.colors.is_empty() {
+            mesh.set_attribute(Mesh::ATTRIBUTE_COLOR, self.colors);
+        }
+
+        if !self.tangents.is_empty() {
+            mesh.set_attribute(Mesh::ATTRIBUTE_TANGENT, self.tangents);
+        }
+
+        mesh
+    }
+
+    // Applies the options of the config that only concern the conversion into a `Mesh`
+    fn into_configured_mesh(self, config : &MeshConfig) -> Mesh {
+        let material_ids = if config.material_ids {
+            Some(self.materials.iter().map(|material| material.id).collect::<Vec<u32>>())
+        } else {
+            None
+        };
+
+        let mut mesh = self.into_mesh(config.topology);
+        if let Some(material_ids) = material_ids {
+            mesh.set_attribute(ATTRIBUTE_MATERIAL_ID, VertexAttributeValues::Uint(material_ids));
+        }
+        mesh
+    }
+
+    // Writes the triangles as a Wavefront OBJ file, for looking at meshes in other tools.
+    // Every vertex gets a `v`, `vn` and `vt` line, so the faces use the same index for all
+    // three.
+    #[cfg(feature = "debug-export")]
+    pub fn to_obj(&self) -> String {
+        use std::fmt::Write;
+
+        let mut obj = String::new();
+        for [x, y, z] in self.positions.iter() {
+            writeln!(obj, "v {} {} {}", x, y, z).unwrap();
+        }
+        for [x, y, z] in self.normals.iter() {
+            writeln!(obj, "vn {} {} {}", x, y, z).unwrap();
+        }
+        for [u, v] in self.uvs.iter() {
+            writeln!(obj, "vt {} {}", u, v).unwrap();
+        }
+
+        // OBJ indices start at 1
+        for triangle in self.indices.chunks_exact(3) {
+            let [a, b, c] = [triangle[0] + 1, triangle[1] + 1, triangle[2] + 1];
+            writeln!(obj, "f {a}/{a}/{a} {b}/{b}/{b} {c}/{c}/{c}", a = a, b = b, c = c).unwrap();
+        }
+        obj
+    }
+}
+
+impl From<ChunkMeshData> for Mesh {
+
+    fn from(data: ChunkMeshData) -> Self {
+        data.into_mesh(MeshTopology::TriangleList)
+    }
+}
+
+// Combines the meshes of several chunks into one, e.g. to draw static scenery with a single
+// draw call. Every mesh is moved by its chunk's position in the world. Tangents are only
+// kept if every mesh has them.
+pub fn merge_meshes(inputs: &[(Point3<f32>, &ChunkMeshData)]) -> Mesh {
+    let mut merged = ChunkMeshData::default();
+
+    for (position, data) in inputs {
+        merged.append(data, position.coords);
+    }
+
+    if merged.tangents.len() != merged.positions.len() {
+        merged.tangents.clear();
+    }
+
+    merged.into()
+}
+
+// Transparent faces are kept in their own mesh so they can be rendered with blending
+// after the opaque geometry.
+// Blocks with a custom model get no faces. If their material has a registered model it is
+// copied into the meshes, otherwise their positions are collected so they can be rendered
+// separately.
+#[derive(Clone)]
+pub struct ChunkMeshes {
+    pub opaque : Mesh,
+    pub transparent : Mesh,
+    pub custom_models : Vec<(Point3<usize>, MaterialID)>,
+    pub stats : MeshStats,
+    // Bounds of the opaque and transparent vertices together
+    pub bounds : MeshBounds,
+}
+
+impl ChunkMeshes {
+
+    fn new(
+        opaque : ChunkMeshData,
+        transparent : ChunkMeshData,
+        custom_models : Vec<(Point3<usize>, MaterialID)>,
+        faces_emitted : usize,
+        faces_culled : usize,
+        config : &MeshConfig
+    ) -> Self {
+        let stats = MeshStats {
+            vertices : opaque.positions.len() + transparent.positions.len(),
+            indices : opaque.indices.len() + transparent.indices.len(),
+            faces_emitted,
+            faces_culled,
+        };
+
+        let mut bounds = MeshBounds::EMPTY;
+        for position in opaque.positions.iter().chain(transparent.positions.iter()) {
+            bounds.include(point![position[0], position[1], position[2]]);
+        }
+
+        ChunkMeshes {
+            opaque : opaque.into_configured_mesh(config),
+            transparent : transparent.into_configured_mesh(config),
+            custom_models,
+            stats,
+            bounds,
+        }
+    }
+
+    // The meshes of a chunk without any blocks. They have the topology and the attributes
+    // of the meshes of other chunks meshed with `config`, so they match the same pipelines.
+    fn empty(config : &MeshConfig) -> Self {
+        let mut meshes = Self::new(ChunkMeshData::default(), ChunkMeshData::default(), Vec::new(), 0, 0, config);
+        for mesh in [&mut meshes.opaque, &mut meshes.transparent].iter_mut() {
+            mesh.set_attribute(Mesh::ATTRIBUTE_COLOR, Vec::<[f32; 4]>::new());
+            if config.tangents {
+                mesh.set_attribute(Mesh::ATTRIBUTE_TANGENT, Vec::<[f32; 4]>::new());
+            }
+        }
+        meshes
+    }
+}
+
+// Counts of a single meshing pass, over both the opaque and the transparent mesh.
+// `faces_emitted` counts the quads of the mesher, so faces merged by the greedy mesher
+// count once and spliced in models not at all.
+// `faces_culled` counts the block faces that were hidden by their neighbors.
+#[derive(Eq, PartialEq, Debug, Default, Copy, Clone)]
+pub struct MeshStats {
+    pub vertices : usize,
+    pub indices : usize,
+    pub faces_emitted : usize,
+    pub faces_culled : usize,
+}
+
+// Axis aligned bounding box of a mesh, for frustum culling. A mesh without vertices has
+// empty bounds, whose `min` lies above `max`, rather than a point at the origin.
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub struct MeshBounds {
+    pub min : Point3<f32>,
+    pub max : Point3<f32>,
+}
+
+impl MeshBounds {
+
+    pub const EMPTY : MeshBounds = MeshBounds {
+        min : point![f32::INFINITY, f32::INFINITY, f32::INFINITY],
+        max : point![f32::NEG_INFINITY, f32::NEG_INFINITY, f32::NEG_INFINITY],
+    };
+
+    pub fn is_empty(&self) -> bool {
+        self.min.x > self.max.x || self.min.y > self.max.y || self.min.z > self.max.z
+    }
+
+    // Grows the bounds to contain `point`
+    pub fn include(&mut self, point : Point3<f32>) {
+        self.min = self.min.inf(&point);
+        self.max = self.max.sup(&point);
+    }
+
+    pub fn union(&self, other : &MeshBounds) -> MeshBounds {
+        MeshBounds {
+            min : self.min.inf(&other.min),
+            max : self.max.sup(&other.max),
+        }
+    }
+}
+
+// Holds the mesh that a chunk's transparent geometry is written to. Chunks without it
+// only get their opaque geometry meshed.
+pub struct TransparentChunkMesh(pub Handle<Mesh>);
+
+// Receives the custom model blocks of a chunk every time it is meshed
+#[derive(Default)]
+pub struct CustomModelBlocks(pub Vec<(Point3<usize>, MaterialID)>);
+
+// The components the meshing systems need to mesh a chunk
+#[derive(Bundle)]
+pub struct ChunkBundle<const X: usize, const Y: usize, const Z: usize> {
+    pub chunk : Chunk<X, Y, Z>,
+    pub mesh : Handle<Mesh>,
+}
+
+impl<const X: usize, const Y: usize, const Z: usize> ChunkBundle<X, Y, Z> {
+
+    // Allocates an empty mesh asset for the chunk
+    pub fn new(chunk: Chunk<X, Y, Z>, meshes: &mut Assets<Mesh>) -> Self {
+        ChunkBundle {
+            chunk,
+            mesh: meshes.add(Mesh::new(PrimitiveTopology::TriangleList)),
+        }
+    }
+}
+
+// Spawns a chunk entity with its mesh handle in place, so it gets meshed right away
+pub fn spawn_chunk<const X: usize, const Y: usize, const Z: usize>(
+    commands: &mut Commands,
+    meshes: &mut Assets<Mesh>,
+    chunk: Chunk<X, Y, Z>,
+) -> Entity {
+    commands.spawn_bundle(ChunkBundle::new(chunk, meshes)).id()
+}
+
+// The chunks adjacent to a chunk, indexed by `AADirection as usize` like `Chunk::neighbors`.
+// Meshers look up the blocks across the chunk's boundary in them, for culling and ambient
+// occlusion. Missing neighbors are treated as air. They use the same block storage `S` as
+// the chunk.
+pub type ChunkNeighborhood<'a, const X: usize, const Y: usize, const Z: usize, S = Chunk<X, Y, Z>> = [Option<&'a S>; 6];
+
+pub trait ChunkMesher {
+
+    // The options the mesher generates meshes with
+    fn config() -> MeshConfig {
+        MeshConfig::DEFAULT
+    }
+
+    fn generate_mesh<const X: usize, const Y: usize, const Z: usize, S: BlockStorage<X, Y, Z>>(
+        chunk: &S,
+        neighbors: &ChunkNeighborhood<X, Y, Z, S>,
+        registry: &MaterialRegistry,
+        atlas: &MaterialAtlas,
+    ) -> Result<ChunkMeshes, MeshError>;
+
+    // Meshes the chunk at a reduced resolution for distant chunks. At level `lod` every
+    // cube of 2^lod blocks along each axis becomes a single cell. Level 0 is the same
+    // as `generate_mesh`.
+    fn generate_mesh_lod<const X: usize, const Y: usize, const Z: usize, S: BlockStorage<X, Y, Z>>(
+        chunk: &S,
+        neighbors: &ChunkNeighborhood<X, Y, Z, S>,
+        registry: &MaterialRegistry,
+        atlas: &MaterialAtlas,
+        lod: u8,
+    ) -> Result<ChunkMeshes, MeshError> {
+        if lod == 0 {
+            Self::generate_mesh(chunk, neighbors, registry, atlas)
+        } else {
+            let config = MeshConfig {
+                lod,
+                ..MeshConfig::default()
+            };
+            generate_mesh_with(chunk, neighbors, registry, atlas, &config)
+        }
+    }
+
+    // Same as `generate_mesh`, but builds the meshes in the given scratch buffers to
+    // avoid allocating new ones for every chunk, see `generate_mesh_with_buffers`
+    fn generate_mesh_with_buffers<const X: usize, const Y: usize, const Z: usize, S: BlockStorage<X, Y, Z>>(
+        chunk: &S,
+        neighbors: &ChunkNeighborhood<X, Y, Z, S>,
+        registry: &MaterialRegistry,
+        atlas: &MaterialAtlas,
+        buffers: &mut ChunkMeshBuffers,
+    ) -> Result<ChunkMeshes, MeshError>;
+}
+
+// Options for `generate_mesh_with`. The defaults produce the same meshes as
+// `GreedyChunkMesher`.
+#[derive(PartialEq, Debug, Copy, Clone)]
+pub struct MeshConfig {
+    // Leave out faces between two blocks that hide each other
+    pub cull_interior : bool,
+    pub ambient_occlusion : bool,
+    // Merge neighboring faces of the same material into larger quads
+    pub greedy : bool,
+    // See `ChunkMesher::generate_mesh_lod`. Levels above 0 always cull and skip
+    // ambient occlusion.
+    pub lod : u8,
+    // Average the normals of vertices that share a position, for renderers that want a
+    // softer look than flat faces
+    pub smooth_normals : bool,
+    // Share vertices between faces where all their attributes match. This makes the
+    // vertex buffers smaller at the cost of some CPU time. The UVs of the faces are laid
+    // out across the chunk for this, so like those of greedy quads they repeat the tile once
+    // per block and have to be wrapped into it by the shader.
+    pub weld_vertices : bool,
+    // Emit tangents for normal mapping
+    pub tangents : bool,
+    // Winding of front faces as seen from outside the block
+    pub winding : Winding,
+    // Added to every vertex position, e.g. the chunk's position in the world to bake
+    // several chunks into one mesh
+    pub offset : Vector3<f32>,
+    pub topology : MeshTopology,
+    // Order in which greedy meshing prefers to merge faces along the axes. Of the two axes
+    // in the plane of a face, quads are first extended along the earlier one. The default
+    // merges along X and Z before Y, which gives long horizontal strips on the sides of
+    // terrain layers.
+    pub sweep_order : [Axis; 3],
+    // Adds the `ATTRIBUTE_MATERIAL_ID` attribute to the meshes
+    pub material_ids : bool,
+    // Longest side of a greedily merged quad in blocks. Longer runs are split into several
+    // quads. Quad sizes are stored as `u8`, so this is at most 255.
+    pub max_quad_size : u8,
+}
+
+#[derive(Eq, PartialEq, Debug, Copy, Clone)]
+pub enum Winding {
+    CounterClockwise,
+    Clockwise,
+}
+
+impl MeshConfig {
+    pub const DEFAULT : MeshConfig = MeshConfig {
+        cull_interior : true,
+        ambient_occlusion : true,
+        greedy : true,
+        lod : 0,
+        smooth_normals : false,
+        weld_vertices : false,
+        tangents : false,
+        winding : Winding::CounterClockwise,
+        offset : Vector3::new(0f32, 0f32, 0f32),
+        topology : MeshTopology::TriangleList,
+        sweep_order : [Axis::X, Axis::Z, Axis::Y],
+        material_ids : false,
+        max_quad_size : u8::MAX,
+    };
+}
+
+impl Default for MeshConfig {
+    fn default() -> Self {
+        MeshConfig::DEFAULT
+    }
+}
+
+pub fn generate_mesh_with<const X: usize, const Y: usize, const Z: usize, S: BlockStorage<X, Y, Z>>(
+    chunk: &S,
+    neighbors: &ChunkNeighborhood<X, Y, Z, S>,
+    registry: &MaterialRegistry,
+    atlas: &MaterialAtlas,
+    config: &MeshConfig,
+) -> Result<ChunkMeshes, MeshError> {
+    let mut buffers = ChunkMeshBuffers::default();
+    let (faces_emitted, faces_culled) = mesh_into_buffers(chunk, neighbors, registry, atlas, config, &mut buffers)?;
+
+    Ok(ChunkMeshes::new(
+        buffers.opaque,
+        buffers.transparent,
+        collect_custom_models(chunk, registry),
+        faces_emitted,
+        faces_culled,
+        config
+    ))
+}
+
+// Like `generate_mesh_with`, but builds the meshes in `buffers`, which are cleared first.
+// The vertex data is moved into the meshes, which leaves the buffers empty. Hand the meshes
+// back with `ChunkMeshBuffers::reclaim` once they are replaced to reuse their allocations
+// for the next chunk.
+pub fn generate_mesh_with_buffers<const X: usize, const Y: usize, const Z: usize, S: BlockStorage<X, Y, Z>>(
+    chunk: &S,
+    neighbors: &ChunkNeighborhood<X, Y, Z, S>,
+    registry: &MaterialRegistry,
+    atlas: &MaterialAtlas,
+    config: &MeshConfig,
+    buffers: &mut ChunkMeshBuffers,
+) -> Result<ChunkMeshes, MeshError> {
+    buffers.clear();
+    let (faces_emitted, faces_culled) = mesh_into_buffers(chunk, neighbors, registry, atlas, config, buffers)?;
+
+    Ok(ChunkMeshes::new(
+        std::mem::take(&mut buffers.opaque),
+        std::mem::take(&mut buffers.transparent),
+        collect_custom_models(chunk, registry),
+        faces_emitted,
+        faces_culled,
+        config
+    ))
+}
+
+#[derive(Eq, PartialEq, Debug, Copy, Clone)]
+pub enum MeshError {
+    // A block of the chunk uses a material that isn't in the registry, which has no
+    // fallback material
+    UnknownMaterial(MaterialID),
+    // The mesh has more vertices than a u32 index can address
+    IndexOverflow,
+}
+
+impl std::fmt::Display for MeshError {
+    fn fmt(&self, f : &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MeshError::UnknownMaterial(id) => write!(f, "material {} isn't registered", id.id),
+            MeshError::IndexOverflow => write!(f, "mesh has too many vertices for u32 indices"),
+        }
+    }
+}
+
+impl std::error::Error for MeshError {}
+
+// Returns the number of emitted and culled faces. Only the quads of the mesher count as
+// emitted faces, not the triangles of the models spliced in after them.
+fn mesh_into_buffers<const X: usize, const Y: usize, const Z: usize, S: BlockStorage<X, Y, Z>>(
+    chunk: &S,
+    neighbors: &ChunkNeighborhood<X, Y, Z, S>,
+    registry: &MaterialRegistry,
+    atlas: &MaterialAtlas,
+    config: &MeshConfig,
+    buffers: &mut ChunkMeshBuffers,
+) -> Result<(usize, usize), MeshError> {
+    let faces_culled = mesh_faces(chunk, neighbors, registry, atlas, config, buffers)?;
+    let faces_emitted = (buffers.opaque.indices.len() + buffers.transparent.indices.len()) / 6;
+
+    // The UVs of every face start at the corner of its own quad, which would keep the
+    // corners of neighboring faces apart
+    if config.weld_vertices {
+        buffers.opaque.align_face_uvs(atlas, chunk.size());
+        buffers.transparent.align_face_uvs(atlas, chunk.size());
+    }
+
+    append_models(chunk, registry, buffers);
+
+    if config.winding == Winding::Clockwise {
+        buffers.opaque.flip_winding();
+        buffers.transparent.flip_winding();
+    }
+
+    if config.smooth_normals {
+        buffers.opaque.smooth_normals();
+        buffers.transparent.smooth_normals();
+    }
+
+    if config.tangents {
+        buffers.opaque.generate_tangents();
+        buffers.transparent.generate_tangents();
+    }
+
+    if config.weld_vertices {
+        buffers.opaque.weld_vertices();
+        buffers.transparent.weld_vertices();
+    }
+
+    buffers.opaque.translate(config.offset);
+    buffers.transparent.translate(config.offset);
+
+    let max_vertices = u32::MAX as usize;
+    if buffers.opaque.vertex_count() > max_vertices || buffers.transparent.vertex_count() > max_vertices {
+        return Err(MeshError::IndexOverflow);
+    }
+
+    Ok((faces_emitted, faces_culled))
+}
+
+// Emits only the block faces, as quads of four consecutive vertices
+fn mesh_faces<const X: usize, const Y: usize, const Z: usize, S: BlockStorage<X, Y, Z>>(
+    chunk: &S,
+    neighbors: &ChunkNeighborhood<X, Y, Z, S>,
+    registry: &MaterialRegistry,
+    atlas: &MaterialAtlas,
+    config: &MeshConfig,
+    buffers: &mut ChunkMeshBuffers,
+) -> Result<usize, MeshError> {
+    let context = MeshingContext::validated(chunk, neighbors, registry, atlas, config)?;
+    let faces_culled = if config.lod > 0 {
+        generate_coarse_mesh(&context, buffers)
+    } else if config.greedy {
+        generate_greedy_mesh(&context, buffers)
+    } else {
+        generate_naive_mesh(&context, buffers)
+    };
+
+    Ok(faces_culled)
+}
+
+// Outlines every face that `generate_mesh_with` would emit for the same config, as a
+// `LineList` mesh with positions only. Useful to check culling and greedy merging.
+// Custom models aren't outlined.
+pub fn generate_wireframe<const X: usize, const Y: usize, const Z: usize, S: BlockStorage<X, Y, Z>>(
+    chunk: &S,
+    neighbors: &ChunkNeighborhood<X, Y, Z, S>,
+    registry: &MaterialRegistry,
+    config: &MeshConfig,
+) -> Result<Mesh, MeshError> {
+    let mut buffers = ChunkMeshBuffers::default();
+    mesh_faces(chunk, neighbors, registry, &MaterialAtlas::default(), config, &mut buffers)?;
+    buffers.opaque.translate(config.offset);
+    buffers.transparent.translate(config.offset);
+
+    let mut positions = Vec::new();
+    let mut indices = Vec::new();
+
+    for data in [&buffers.opaque, &buffers.transparent].iter() {
+        for quad in data.positions.chunks_exact(4) {
+            let base = positions.len() as u32;
+            positions.extend_from_slice(quad);
+            for corner in 0..4 {
+                indices.push(base + corner);
+                indices.push(base + (corner + 1) % 4);
+            }
+        }
+    }
+
+    let mut mesh = Mesh::new(PrimitiveTopology::LineList);
+    let vertex_count = positions.len();
+    mesh.set_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+    mesh.set_indices(Some(compact_indices(vertex_count, indices)));
+
+    Ok(mesh)
+}
+
+// Meshes the chunk like `generate_mesh_with`, but gives every triangle its own vertices
+// and vertex color, so that the two triangles of each face can be told apart. This shows
+// the winding and orientation of the faces in-engine.
+#[cfg(feature = "debug-export")]
+pub fn generate_triangle_debug_mesh<const X: usize, const Y: usize, const Z: usize, S: BlockStorage<X, Y, Z>>(
+    chunk: &S,
+    neighbors: &ChunkNeighborhood<X, Y, Z, S>,
+    registry: &MaterialRegistry,
+    config: &MeshConfig,
+) -> Result<Mesh, MeshError> {
+    let mut buffers = ChunkMeshBuffers::default();
+    mesh_faces(chunk, neighbors, registry, &MaterialAtlas::default(), config, &mut buffers)?;
+    buffers.opaque.translate(config.offset);
+    buffers.transparent.translate(config.offset);
+
+    let mut split = ChunkMeshData::default();
+    let mut triangle_count = 0;
+
+    for data in [&buffers.opaque, &buffers.transparent].iter() {
+        for triangle in data.indices.chunks_exact(3) {
+            let color = debug_index_color(triangle_count);
+            for &index in triangle {
+                let index = index as usize;
+                split.indices.push(split.positions.len() as u32);
+                split.positions.push(data.positions[index]);
+                split.normals.push(data.normals[index]);
+                split.uvs.push(data.uvs[index]);
+                split.colors.push(color);
+            }
+            triangle_count += 1;
+        }
+    }
+
+    Ok(split.into_mesh(MeshTopology::TriangleList))
+}
+
+type ChunkMeshTargets<'a, const X: usize, const Y: usize, const Z: usize> = (
+    Entity,
+    &'a Chunk<X, Y, Z>,
+    &'a Handle<Mesh>,
+    Option<&'a TransparentChunkMesh>,
+    Option<&'a mut CustomModelBlocks>,
+);
+
+// What iterating over a `ChunkMeshTargets` query yields
+type ChunkMeshTargetItem<'a, const X: usize, const Y: usize, const Z: usize> = (
+    Entity,
+    &'a Chunk<X, Y, Z>,
+    &'a Handle<Mesh>,
+    Option<&'a TransparentChunkMesh>,
+    Option<Mut<'a, CustomModelBlocks>>,
+);
+
+pub fn chunk_meshing_system<Mesher: ChunkMesher, const X: usize, const Y: usize, const Z: usize>(
+    mut buffers: Local<ChunkMeshBuffers>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    registry: Res<MaterialRegistry>,
+    atlas: Res<MaterialAtlas>,
+    mut query: Query<ChunkMeshTargets<X, Y, Z>>,
+    chunks: Query<&Chunk<X, Y, Z>>,
+) {
+    mesh_chunks::<Mesher, X, Y, Z>(query.iter_mut(), &chunks, &mut buffers, &mut meshes, &registry, &atlas);
+}
+
+// Same as `chunk_meshing_system`, but only looks at chunks that bevy's change detection
+// saw being modified since the last run, instead of checking every chunk. Chunks whose
+// mesh assets weren't available are remembered and retried on every run until they are,
+// as waiting for their assets doesn't modify them.
+pub fn changed_chunk_meshing_system<Mesher: ChunkMesher, const X: usize, const Y: usize, const Z: usize>(
+    // Scratch buffers and the chunks waiting for their mesh assets
+    mut state: Local<(ChunkMeshBuffers, HashSet<Entity>)>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    registry: Res<MaterialRegistry>,
+    atlas: Res<MaterialAtlas>,
+    changed: Query<Entity, Changed<Chunk<X, Y, Z>>>,
+    mut query: Query<ChunkMeshTargets<X, Y, Z>>,
+    chunks: Query<&Chunk<X, Y, Z>>,
+) {
+    let (buffers, pending) = &mut *state;
+    let mut candidates = std::mem::take(pending);
+    candidates.extend(changed.iter());
+
+    for entity in candidates {
+        if let Ok(target) = query.get_mut(entity) {
+            if !mesh_chunk::<Mesher, X, Y, Z>(target, &chunks, buffers, &mut meshes, &registry, &atlas) {
+                pending.insert(entity);
+            }
+        }
+    }
+}
+
+fn mesh_chunks<'a, Mesher: ChunkMesher, const X: usize, const Y: usize, const Z: usize>(
+    targets: impl Iterator<Item = ChunkMeshTargetItem<'a, X, Y, Z>>,
+    chunks: &Query<&Chunk<X, Y, Z>>,
+    buffers: &mut ChunkMeshBuffers,
+    meshes: &mut Assets<Mesh>,
+    registry: &MaterialRegistry,
+    atlas: &MaterialAtlas,
+) {
+    for target in targets {
+        mesh_chunk::<Mesher, X, Y, Z>(target, chunks, buffers, meshes, registry, atlas);
+    }
+}
+
+// Meshes the chunk if it changed. Returns false if it has to wait for its mesh assets.
+fn mesh_chunk<Mesher: ChunkMesher, const X: usize, const Y: usize, const Z: usize>(
+    target: ChunkMeshTargetItem<X, Y, Z>,
+    chunks: &Query<&Chunk<X, Y, Z>>,
+    buffers: &mut ChunkMeshBuffers,
+    meshes: &mut Assets<Mesh>,
+    registry: &MaterialRegistry,
+    atlas: &MaterialAtlas,
+) -> bool {
+    let (entity, chunk, mesh_handle, transparent_mesh, custom_models) = target;
+
+    if !chunk.has_changed() {
+        return true;
+    }
+
+    if !ready_to_mesh(meshes, mesh_handle, transparent_mesh) {
+        return false;
+    }
+
+    let neighbors = resolve_neighbors(chunk, |entity| chunks.get(entity).ok());
+    let new_meshes = generate_chunk_meshes::<Mesher, X, Y, Z>(entity, chunk, &neighbors, registry, atlas, Some(&mut *buffers));
+
+    match new_meshes {
+        Ok(new_meshes) => {
+            let (opaque, transparent) = apply_chunk_meshes(meshes, mesh_handle, transparent_mesh, custom_models, new_meshes);
+            buffers.reclaim(opaque, transparent);
+        },
+        Err(error) => {
+            warn!("Failed to mesh chunk: {}", error);
+            chunk.mark_mesh_failed();
+        },
+    }
+
+    true
+}
+
+// Meshes the chunk `entity` with the mesher, in `buffers` if there are any. Chunks of air
+// get empty meshes in the mesher's layout without running the mesher.
+fn generate_chunk_meshes<Mesher: ChunkMesher, const X: usize, const Y: usize, const Z: usize>(
+    entity: Entity,
+    chunk: &Chunk<X, Y, Z>,
+    neighbors: &ChunkNeighborhood<X, Y, Z>,
+    registry: &MaterialRegistry,
+    atlas: &MaterialAtlas,
+    buffers: Option<&mut ChunkMeshBuffers>,
+) -> Result<ChunkMeshes, MeshError> {
+    if chunk.is_empty() {
+        return Ok(ChunkMeshes::empty(&Mesher::config()));
+    }
+
+    generate_traced(entity, || match buffers {
+        Some(buffers) => Mesher::generate_mesh_with_buffers(chunk, neighbors, registry, atlas, buffers),
+        None => Mesher::generate_mesh(chunk, neighbors, registry, atlas),
+    })
+}
+
+// Looks up the chunk's neighbors, indexed like `Chunk::neighbors`. Neighbors that `lookup`
+// can't find, e.g. despawned ones, are left out.
+fn resolve_neighbors<T, const X: usize, const Y: usize, const Z: usize>(
+    chunk: &Chunk<X, Y, Z>,
+    mut lookup: impl FnMut(Entity) -> Option<T>,
+) -> [Option<T>; 6] {
+    let mut neighbors : [Option<T>; 6] = Default::default();
+    for (neighbor, entity) in neighbors.iter_mut().zip(chunk.neighbors()) {
+        *neighbor = entity.and_then(&mut lookup);
+    }
+    neighbors
+}
+
+// Whether a changed chunk can be meshed now, which needs its mesh assets
+fn ready_to_mesh(
+    meshes: &Assets<Mesh>,
+    mesh_handle: &Handle<Mesh>,
+    transparent_mesh: Option<&TransparentChunkMesh>,
+) -> bool {
+    let available = mesh_assets_available(meshes, mesh_handle, transparent_mesh);
+    if !available {
+        debug!("Skipping chunk meshing, its mesh assets aren't available");
+    }
+    available
+}
+
+// Runs a mesher on the chunk `entity`. With the `trace` feature the call is wrapped in a
+// `generate_mesh` span tagged with the entity and the number of vertices produced.
+#[cfg(feature = "trace")]
+fn generate_traced(
+    entity: Entity,
+    generate: impl FnOnce() -> Result<ChunkMeshes, MeshError>
+) -> Result<ChunkMeshes, MeshError> {
+    use bevy::utils::tracing::{field, info_span};
+
+    let span = info_span!("generate_mesh", entity = ?entity, vertices = field::Empty);
+    let _guard = span.enter();
+
+    let result = generate();
+    if let Ok(meshes) = &result {
+        span.record("vertices", meshes.stats.vertices as u64);
+    }
+    result
+}
+
+#[cfg(not(feature = "trace"))]
+fn generate_traced(
+    _entity: Entity,
+    generate: impl FnOnce() -> Result<ChunkMeshes, MeshError>
+) -> Result<ChunkMeshes, MeshError> {
+    generate()
+}
+
+// Same as `chunk_meshing_system`, but splits the work into two passes: all changed chunks
+// are meshed first, then the results are written to the mesh assets in one go, which keeps
+// the write accesses to the assets and the chunks' components together.
+pub fn batched_chunk_meshing_system<Mesher: ChunkMesher, const X: usize, const Y: usize, const Z: usize>(
+    mut buffers: Local<ChunkMeshBuffers>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    registry: Res<MaterialRegistry>,
+    atlas: Res<MaterialAtlas>,
+    mut query: Query<ChunkMeshTargets<X, Y, Z>>,
+    chunks: Query<(Entity, &Chunk<X, Y, Z>)>,
+) {
+    let jobs = collect_mesh_jobs(&chunks, &mut query, &meshes);
+
+    let results: Vec<_> = jobs.iter()
+        .map(|(entity, chunk, neighbors)| {
+            (*entity, generate_chunk_meshes::<Mesher, X, Y, Z>(*entity, chunk, neighbors, &registry, &atlas, Some(&mut buffers)))
+        })
+        .collect();
+
+    apply_mesh_results(results, &mut query, &mut meshes, Some(&mut buffers));
+}
+
+// Same as `chunk_meshing_system`, but generates the meshes of all changed chunks in
+// parallel on the compute task pool before writing them back. This uses bevy's pool
+// rather than rayon: bevy already runs its systems on it, and a second pool would
+// compete with it for the same cores.
+#[cfg(feature = "parallel")]
+pub fn parallel_chunk_meshing_system<Mesher: ChunkMesher, const X: usize, const Y: usize, const Z: usize>(
+    pool: Res<ComputeTaskPool>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    registry: Res<MaterialRegistry>,
+    atlas: Res<MaterialAtlas>,
+    mut query: Query<ChunkMeshTargets<X, Y, Z>>,
+    chunks: Query<(Entity, &Chunk<X, Y, Z>)>,
+) {
+    let jobs = collect_mesh_jobs(&chunks, &mut query, &meshes);
+
+    let registry = &*registry;
+    let atlas = &*atlas;
+    let results = pool.scope(|scope| {
+        for (entity, chunk, neighbors) in jobs.iter() {
+            scope.spawn(async move {
+                (*entity, generate_chunk_meshes::<Mesher, X, Y, Z>(*entity, chunk, neighbors, registry, atlas, None))
+            });
+        }
+    });
+
+    apply_mesh_results(results, &mut query, &mut meshes, None);
+}
+
+// A changed chunk whose mesh assets are available, together with its neighbors
+type MeshJob<'a, const X: usize, const Y: usize, const Z: usize> = (Entity, &'a Chunk<X, Y, Z>, ChunkNeighborhood<'a, X, Y, Z>);
+
+// Finds the chunks the batched meshing systems have to mesh
+fn collect_mesh_jobs<'a, const X: usize, const Y: usize, const Z: usize>(
+    chunks: &'a Query<(Entity, &Chunk<X, Y, Z>)>,
+    query: &mut Query<ChunkMeshTargets<X, Y, Z>>,
+    meshes: &Assets<Mesh>,
+) -> Vec<MeshJob<'a, X, Y, Z>> {
+    let mut jobs = Vec::new();
+    for (entity, chunk) in chunks.iter() {
+
+        if !chunk.has_changed() {
+            continue;
+        }
+
+        match query.get_mut(entity) {
+            Ok((_, _, mesh_handle, transparent_mesh, _)) => {
+                if !ready_to_mesh(meshes, mesh_handle, transparent_mesh) {
+                    continue;
+                }
+            },
+            Err(_) => continue,
+        }
+
+        let neighbors = resolve_neighbors(chunk, |entity| chunks.get(entity).ok().map(|(_, chunk)| chunk));
+        jobs.push((entity, chunk, neighbors));
+    }
+    jobs
+}
+
+// Writes the meshes generated by the batched meshing systems back to their chunks. The
+// replaced meshes are reclaimed into `buffers` if there are any.
+fn apply_mesh_results<const X: usize, const Y: usize, const Z: usize>(
+    results: Vec<(Entity, Result<ChunkMeshes, MeshError>)>,
+    query: &mut Query<ChunkMeshTargets<X, Y, Z>>,
+    meshes: &mut Assets<Mesh>,
+    mut buffers: Option<&mut ChunkMeshBuffers>,
+) {
+    for (entity, new_meshes) in results {
+        if let Ok((_, chunk, mesh_handle, transparent_mesh, custom_models)) = query.get_mut(entity) {
+            match new_meshes {
+                Ok(new_meshes) => {
+                    let (opaque, transparent) = apply_chunk_meshes(meshes, mesh_handle, transparent_mesh, custom_models, new_meshes);
+                    if let Some(buffers) = buffers.as_deref_mut() {
+                        buffers.reclaim(opaque, transparent);
+                    }
+                },
+                Err(error) => {
+                    warn!("Failed to mesh chunk: {}", error);
+                    chunk.mark_mesh_failed();
+                },
+            }
+        }
+    }
+}
+
+// A mesh being generated on the `AsyncComputeTaskPool` for the chunk it is attached to,
+// from a snapshot of the chunk at `revision`
+pub struct ChunkMeshTask {
+    task : Task<Result<ChunkMeshes, MeshError>>,
+    revision : u64,
+}
+
+// Starts meshing changed chunks in the background, so that large amounts of meshing don't
+// stall the frame. The meshers work on snapshots of the chunks and their neighbors, which
+// are shared between the tasks started in the same run, like the copy of the registry and
+// the atlas that is kept until either changes. Run `async_chunk_mesh_apply_system` to
+// write the finished meshes back. Chunks that already have a task running are picked up
+// again once it finished.
+// Label the systems to run `async_chunk_mesh_apply_system`, then this system, then
+// `chunk_end_of_tick_system`, so that finished chunks are queued again in the same tick.
+#[allow(clippy::too_many_arguments)]
+pub fn async_chunk_meshing_system<Mesher: ChunkMesher, const X: usize, const Y: usize, const Z: usize>(
+    mut commands: Commands,
+    pool: Res<AsyncComputeTaskPool>,
+    mut shared: Local<Option<Arc<(MaterialRegistry, MaterialAtlas)>>>,
+    meshes: Res<Assets<Mesh>>,
+    registry: Res<MaterialRegistry>,
+    atlas: Res<MaterialAtlas>,
+    query: Query<ChunkMeshQueued<X, Y, Z>, Without<ChunkMeshTask>>,
+    chunks: Query<&Chunk<X, Y, Z>>,
+) {
+    if shared.is_none() || registry.is_changed() || atlas.is_changed() {
+        *shared = Some(Arc::new((registry.clone(), atlas.clone())));
+    }
+
+    let mut snapshots : HashMap<Entity, Arc<Chunk<X, Y, Z>>> = HashMap::new();
+    let mut snapshot = |entity: Entity| {
+        if let Some(snapshot) = snapshots.get(&entity) {
+            return Some(snapshot.clone());
+        }
+        let snapshot = Arc::new(chunks.get(entity).ok()?.clone());
+        snapshots.insert(entity, snapshot.clone());
+        Some(snapshot)
+    };
+
+    for (entity, chunk, mesh_handle, transparent_mesh) in query.iter() {
+
+        if !chunk.has_changed() || !ready_to_mesh(&meshes, mesh_handle, transparent_mesh) {
+            continue;
+        }
+
+        let neighbors = resolve_neighbors(chunk, &mut snapshot);
+        let snapshot = match snapshot(entity) {
+            Some(snapshot) => snapshot,
+            None => continue,
+        };
+        let shared = shared.clone().unwrap();
+
+        let task = pool.spawn(async move {
+            let (registry, atlas) = &*shared;
+            let neighbors = neighbors.each_ref().map(Option::as_deref);
+            generate_chunk_meshes::<Mesher, X, Y, Z>(entity, &snapshot, &neighbors, registry, atlas, None)
+        });
+
+        chunk.mark_mesh_queued();
+        commands.entity(entity).insert(ChunkMeshTask { task, revision : chunk.revision() });
+    }
+}
+
+// How often the background meshing of an unchanged chunk is retried after it failed
+const ASYNC_MESH_ATTEMPTS : u32 = 3;
+
+// Writes the meshes of finished `ChunkMeshTask`s back into the mesh assets and clears the
+// chunk's change flag, which `chunk_end_of_tick_system` leaves alone while a task is
+// running. Results of chunks that changed after their snapshot was taken are dropped and
+// the chunk stays changed, so it is meshed anew. Chunks that fail to mesh stay changed
+// too, until they failed `ASYNC_MESH_ATTEMPTS` times in a row without being modified.
+// They are then left alone until they change again.
+pub fn async_chunk_mesh_apply_system<const X: usize, const Y: usize, const Z: usize>(
+    mut commands: Commands,
+    // Failed attempts in a row and the revision they were made at, by chunk
+    mut failures: Local<HashMap<Entity, (u64, u32)>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut query: Query<ChunkMeshTaskTargets<X, Y, Z>>,
+) {
+    for (entity, mut chunk, mesh_handle, transparent_mesh, custom_models, mut task) in query.iter_mut() {
+
+        let result = match poll_task(&mut task.task) {
+            Some(result) => result,
+            None => continue,
+        };
+        commands.entity(entity).remove::<ChunkMeshTask>();
+
+        if chunk.revision() != task.revision {
+            chunk.set_change();
+            continue;
+        }
+
+        match result {
+            Ok(new_meshes) => {
+                apply_chunk_meshes(&mut meshes, mesh_handle, transparent_mesh, custom_models, new_meshes);
+                chunk.clear_change();
+                failures.remove(&entity);
+            },
+            Err(error) => {
+                let attempts = match failures.get(&entity) {
+                    Some((revision, attempts)) if *revision == task.revision => attempts + 1,
+                    _ => 1,
+                };
+
+                if attempts < ASYNC_MESH_ATTEMPTS {
+                    warn!("Failed to mesh chunk: {}", error);
+                    failures.insert(entity, (task.revision, attempts));
+                } else {
+                    warn!("Failed to mesh chunk {} times, giving up until it changes: {}", attempts, error);
+                    failures.remove(&entity);
+                    chunk.clear_change();
+                }
+            },
+        }
+    }
+}
+
+type ChunkMeshQueued<'a, const X: usize, const Y: usize, const Z: usize> = (
+    Entity,
+    &'a Chunk<X, Y, Z>,
+    &'a Handle<Mesh>,
+    Option<&'a TransparentChunkMesh>,
+);
+
+type ChunkMeshTaskTargets<'a, const X: usize, const Y: usize, const Z: usize> = (
+    Entity,
+    &'a mut Chunk<X, Y, Z>,
+    &'a Handle<Mesh>,
+    Option<&'a TransparentChunkMesh>,
+    Option<&'a mut CustomModelBlocks>,
+    &'a mut ChunkMeshTask,
+);
+
+// Returns the output of the task if it finished, without blocking
+fn poll_task<T>(task: &mut Task<T>) -> Option<T> {
+    let mut context = std::task::Context::from_waker(std::task::Waker::noop());
+    match Pin::new(task).poll(&mut context) {
+        std::task::Poll::Ready(output) => Some(output),
+        std::task::Poll::Pending => None,
+    }
+}
+
+// Mesh handles can be stale or point to assets that are still being loaded
+fn mesh_assets_available(
+    meshes: &Assets<Mesh>,
+    mesh_handle: &Handle<Mesh>,
+    transparent_mesh: Option<&TransparentChunkMesh>,
+) -> bool {
+    meshes.get(mesh_handle).is_some()
+        && transparent_mesh
+            .map(|TransparentChunkMesh(transparent_handle)| meshes.get(transparent_handle).is_some())
+            .unwrap_or(true)
+}
+
+// Returns the replaced opaque and transparent meshes, whose buffers can be reused
+fn apply_chunk_meshes(
+    meshes: &mut Assets<Mesh>,
+    mesh_handle: &Handle<Mesh>,
+    transparent_mesh: Option<&TransparentChunkMesh>,
+    custom_models: Option<Mut<CustomModelBlocks>>,
+    new_meshes: ChunkMeshes,
+) -> (Option<Mesh>, Option<Mesh>) {
+    let ChunkMeshes { opaque, transparent, custom_models : new_custom_models, .. } = new_meshes;
+
+    let opaque = meshes.get_mut(mesh_handle)
+        .map(|mesh| std::mem::replace(mesh, opaque));
+
+    let transparent = match transparent_mesh {
+        Some(TransparentChunkMesh(transparent_handle)) => meshes.get_mut(transparent_handle)
+            .map(|mesh| std::mem::replace(mesh, transparent)),
+        None => None,
+    };
+
+    if let Some(mut custom_models) = custom_models {
+        custom_models.0 = new_custom_models;
+    }
+
+    (opaque, transparent)
+}
+
+trait FaceInserter {
+    fn corners(
+        block_pos: Point3<usize>,
+        dimensions: Vector2<u8>
+    ) -> [[f32; 3]; 4];
+}
+
+struct FaceInserterImpl<const DIR: usize>;
+
+fn face_corners(
+    direction: AADirection,
+    block_pos: Point3<usize>,
+    dimensions: Vector2<u8>
+) -> [[f32; 3]; 4] {
+    match direction {
+        AADirection::XPositive => FaceInserterImpl::<0>::corners(block_pos, dimensions),
+        AADirection::XNegative => FaceInserterImpl::<1>::corners(block_pos, dimensions),
+        AADirection::YPositive => FaceInserterImpl::<2>::corners(block_pos, dimensions),
+        AADirection::YNegative => FaceInserterImpl::<3>::corners(block_pos, dimensions),
+        AADirection::ZPositive => FaceInserterImpl::<4>::corners(block_pos, dimensions),
+        AADirection::ZNegative => FaceInserterImpl::<5>::corners(block_pos, dimensions),
+    }
+}
+
+// Vertex brightness for each ambient occlusion level, from fully occluded to open
+const OCCLUSION_BRIGHTNESS : [f32; 4] = [0.4, 0.6, 0.8, 1.0];
+
+// Everything about a quad besides its geometry
+struct QuadAppearance {
+    material: MaterialID,
+    uv_rect: UvRect,
+    tint: [f32; 4],
+    occlusion: [u8; 4],
+    // Brightness of each corner from the light levels around it, 1 is fully lit
+    light: [f32; 4],
+}
+
+// Corners are given as [top left, bottom left, bottom right, top right] as seen
+// from outside the block, which winds them counter-clockwise. The texture's u
+// axis runs left to right along `dimensions.x` and v runs top to bottom along
+// `dimensions.y`.
+fn insert_quad(
+    corners: [[f32; 3]; 4],
+    dimensions: Vector2<u8>,
+    direction: AADirection,
+    appearance: QuadAppearance,
+    mesh: &mut ChunkMeshData
+) {
+    let QuadAppearance { material, uv_rect, tint, occlusion, light } = appearance;
+    let base_vertex_idx = mesh.positions.len() as u32;
+    let normal = direction.normal();
+    let u = dimensions.x as f32;
+    let v = dimensions.y as f32;
+    let uvs = [[0f32, 0f32], [0f32, v], [u, v], [u, 0f32]];
+
+    for (((corner, uv), level), light) in corners.iter().zip(uvs.iter()).zip(occlusion.iter()).zip(light.iter()) {
+        let brightness = OCCLUSION_BRIGHTNESS[*level as usize] * light;
+        mesh.positions.push(*corner);
+        mesh.normals.push(normal);
+        mesh.uvs.push(uv_rect.map(*uv));
+        mesh.colors.push([tint[0] * brightness, tint[1] * brightness, tint[2] * brightness, tint[3]]);
+        mesh.materials.push(material);
+    }
+
+    // Split the quad along the diagonal with the brighter corners, otherwise the
+    // occlusion of a single corner bleeds across the whole quad
+    let first = if occlusion[0] + occlusion[2] < occlusion[1] + occlusion[3] {
+        1
+    } else {
+        0
+    };
+
+    // indices
+    mesh.indices.push(base_vertex_idx + first);
+    mesh.indices.push(base_vertex_idx + first + 1);
+    mesh.indices.push(base_vertex_idx + first + 2);
+    mesh.indices.push(base_vertex_idx + first + 2);
+    mesh.indices.push(base_vertex_idx + (first + 3) % 4);
+    mesh.indices.push(base_vertex_idx + first);
+}
+
+// Direction in which the u texture coordinate of a face increases, see `insert_quad`
+fn face_tangent(direction: AADirection) -> [f32; 3] {
+    match direction {
+        AADirection::XPositive => [0f32, 0f32, -1f32],
+        AADirection::XNegative => [0f32, 0f32, 1f32],
+        AADirection::YPositive => [1f32, 0f32, 0f32],
+        AADirection::YNegative => [-1f32, 0f32, 0f32],
+        AADirection::ZPositive => [1f32, 0f32, 0f32],
+        AADirection::ZNegative => [-1f32, 0f32, 0f32],
+    }
+}
+
+fn base_position(block_pos: Point3<usize>) -> Point3<f32> {
+    point![block_pos.x as f32, block_pos.y as f32, block_pos.z as f32]
+}
+
+// X faces span u along Z and v along Y
+impl FaceInserter for FaceInserterImpl<0> {
+    fn corners(block_pos: Point3<usize>, dimensions: Vector2<u8>) -> [[f32; 3]; 4] {
+
+        let base_pos = base_position(block_pos);
+        let x = base_pos.x + 1f32;
+        let u = dimensions.x as f32;
+        let v = dimensions.y as f32;
+
+        [
+            [x, base_pos.y + v, base_pos.z + u],
+            [x, base_pos.y, base_pos.z + u],
+            [x, base_pos.y, base_pos.z],
+            [x, base_pos.y + v, base_pos.z],
+        ]
+    }
+}
+
+impl FaceInserter for FaceInserterImpl<1> {
+    fn corners(block_pos: Point3<usize>, dimensions: Vector2<u8>) -> [[f32; 3]; 4] {
+
+        let base_pos = base_position(block_pos);
+        let x = base_pos.x;
+        let u = dimensions.x as f32;
+        let v = dimensions.y as f32;
+
+        [
+            [x, base_pos.y + v, base_pos.z],
+            [x, base_pos.y, base_pos.z],
+            [x, base_pos.y, base_pos.z + u],
+            [x, base_pos.y + v, base_pos.z + u],
+        ]
+    }
+}
+
+// Y faces span u along X and v along Z
+impl FaceInserter for FaceInserterImpl<2> {
+    fn corners(block_pos: Point3<usize>, dimensions: Vector2<u8>) -> [[f32; 3]; 4] {
+
+        let base_pos = base_position(block_pos);
+        let y = base_pos.y + 1f32;
+        let u = dimensions.x as f32;
+        let v = dimensions.y as f32;
+
+        [
+            [base_pos.x, y, base_pos.z],
+            [base_pos.x, y, base_pos.z + v],
+            [base_pos.x + u, y, base_pos.z + v],
+            [base_pos.x + u, y, base_pos.z],
+        ]
+    }
+}
+
+impl FaceInserter for FaceInserterImpl<3> {
+    fn corners(block_pos: Point3<usize>, dimensions: Vector2<u8>) -> [[f32; 3]; 4] {
+
+        let base_pos = base_position(block_pos);
+        let y = base_pos.y;
+        let u = dimensions.x as f32;
+        let v = dimensions.y as f32;
+
+        [
+            [base_pos.x + u, y, base_pos.z],
+            [base_pos.x + u, y, base_pos.z + v],
+            [base_pos.x, y, base_pos.z + v],
+            [base_pos.x, y, base_pos.z],
+        ]
+    }
+}
+
+// Z faces span u along X and v along Y
+impl FaceInserter for FaceInserterImpl<4> {
+    fn corners(block_pos: Point3<usize>, dimensions: Vector2<u8>) -> [[f32; 3]; 4] {
+
+        let base_pos = base_position(block_pos);
+        let z = base_pos.z + 1f32;
+        let u = dimensions.x as f32;
+        let v = dimensions.y as f32;
+
+        [
+            [base_pos.x, base_pos.y + v, z],
+            [base_pos.x, base_pos.y, z],
+            [base_pos.x + u, base_pos.y, z],
+            [base_pos.x + u, base_pos.y + v, z],
+        ]
+    }
+}
+
+impl FaceInserter for FaceInserterImpl<5> {
+    fn corners(block_pos: Point3<usize>, dimensions: Vector2<u8>) -> [[f32; 3]; 4] {
+
+        let base_pos = base_position(block_pos);
+        let z = base_pos.z;
+        let u = dimensions.x as f32;
+        let v = dimensions.y as f32;
+
+        [
+            [base_pos.x + u, base_pos.y + v, z],
+            [base_pos.x + u, base_pos.y, z],
+            [base_pos.x, base_pos.y, z],
+            [base_pos.x, base_pos.y + v, z],
+        ]
+    }
+}
+
+fn adjacent_in_chunk(
+    size: Vector3<usize>,
+    x: usize,
+    y: usize,
+    z: usize,
+    direction: AADirection
+) -> Option<(usize, usize, usize)> {
+    match direction {
+        AADirection::XPositive if x + 1 < size.x => Some((x + 1, y, z)),
+        AADirection::XNegative if x > 0 => Some((x - 1, y, z)),
+        AADirection::YPositive if y + 1 < size.y => Some((x, y + 1, z)),
+        AADirection::YNegative if y > 0 => Some((x, y - 1, z)),
+        AADirection::ZPositive if z + 1 < size.z => Some((x, y, z + 1)),
+        AADirection::ZNegative if z > 0 => Some((x, y, z - 1)),
+        _ => None,
+    }
+}
+
+// Wraps a coordinate on the edge of a chunk onto the facing edge of its neighbor
+fn adjacent_in_neighbor(
+    size: Vector3<usize>,
+    x: usize,
+    y: usize,
+    z: usize,
+    direction: AADirection
+) -> (usize, usize, usize) {
+    match direction {
+        AADirection::XPositive => (0, y, z),
+        AADirection::XNegative => (size.x - 1, y, z),
+        AADirection::YPositive => (x, 0, z),
+        AADirection::YNegative => (x, size.y - 1, z),
+        AADirection::ZPositive => (x, y, 0),
+        AADirection::ZNegative => (x, y, size.z - 1),
+    }
+}
+
+// Occlusion level of a face corner from the two blocks beside it and the one diagonal
+// to it, all in the layer in front of the face. 0 is fully occluded and 3 is open.
+fn vertex_occlusion(side_a: bool, side_b: bool, corner: bool) -> u8 {
+    if side_a && side_b {
+        0
+    } else {
+        3 - (side_a as u8 + side_b as u8 + corner as u8)
+    }
+}
+
+// Visits every block of a storage together with its coordinates, in index order
+fn storage_blocks<const X: usize, const Y: usize, const Z: usize, S: BlockStorage<X, Y, Z>>(
+    chunk: &S
+) -> impl Iterator<Item = (usize, usize, usize, Block)> + '_ {
+    let size = chunk.size();
+    (0..size.x * size.y * size.z).map(move |index| {
+        let (x, y, z) = (index % size.x, (index / size.x) % size.y, index / (size.x * size.y));
+        (x, y, z, chunk.block_at(x, y, z))
+    })
+}
+
+// The chunk among `chunk` and its neighbors that contains a position relative to `chunk`,
+// and the position within it. Only reaches the direct neighbors, not the chunks diagonally across an edge.
+fn locate_world<'a, const X: usize, const Y: usize, const Z: usize, S: BlockStorage<X, Y, Z>>(
+    chunk: &'a S,
+    neighbors: &ChunkNeighborhood<'a, X, Y, Z, S>,
+    pos: [i32; 3]
+) -> Option<(&'a S, [usize; 3])> {
+    let size = chunk.size().map(|extent| extent as i32);
+    let mut local = [0usize; 3];
+    let mut outside = None;
+
+    for axis in 0..3 {
+        let (coord, side) = if pos[axis] < 0 {
+            (pos[axis] + size[axis], Some(false))
+        } else if pos[axis] >= size[axis] {
+            (pos[axis] - size[axis], Some(true))
+        } else {
+            (pos[axis], None)
+        };
+
+        if coord < 0 || coord >= size[axis] {
+            return None;
+        }
+        local[axis] = coord as usize;
+
+        if let Some(positive) = side {
+            if outside.is_some() {
+                return None;
+            }
+            outside = Some(AADirection::from_axis_sign(Axis::ALL[axis], positive));
+        }
+    }
+
+    let chunk = match outside {
+        Some(direction) => neighbors[direction as usize]?,
+        None => chunk,
+    };
+
+    Some((chunk, local))
+}
+
+// Everything a mesher needs to look up while meshing a single chunk
+struct MeshingContext<'a, const X: usize, const Y: usize, const Z: usize, S = Chunk<X, Y, Z>> {
+    chunk: &'a S,
+    neighbors: &'a ChunkNeighborhood<'a, X, Y, Z, S>,
+    registry: &'a MaterialRegistry,
+    atlas: &'a MaterialAtlas,
+    config: &'a MeshConfig,
+}
+
+impl<'a, const X: usize, const Y: usize, const Z: usize, S: BlockStorage<X, Y, Z>> MeshingContext<'a, X, Y, Z, S> {
+
+    // Fails if the chunk contains a material that isn't registered and there is no fallback
+    fn validated(
+        chunk: &'a S,
+        neighbors: &'a ChunkNeighborhood<'a, X, Y, Z, S>,
+        registry: &'a MaterialRegistry,
+        atlas: &'a MaterialAtlas,
+        config: &'a MeshConfig,
+    ) -> Result<Self, MeshError> {
+        if registry.fallback().is_none() {
+            if let Some((_, _, _, block)) = storage_blocks(chunk).find(|(_, _, _, block)| !registry.contains(block.material)) {
+                return Err(MeshError::UnknownMaterial(block.material));
+            }
+        }
+
+        Ok(MeshingContext {
+            chunk,
+            neighbors,
+            registry,
+            atlas,
+            config,
+        })
+    }
+
+    // Returns the block next to (x, y, z) in `direction`, looking into the neighboring
+    // chunk on the boundary. Returns `None` if that neighbor isn't available.
+    fn adjacent_block(&self, x: usize, y: usize, z: usize, direction: AADirection) -> Option<Block> {
+        let size = self.chunk.size();
+        match adjacent_in_chunk(size, x, y, z, direction) {
+            Some((nx, ny, nz)) => Some(self.chunk.block_at(nx, ny, nz)),
+            None => self.neighbors[direction as usize].map(|neighbor| {
+                let (nx, ny, nz) = adjacent_in_neighbor(size, x, y, z, direction);
+                neighbor.block_at(nx, ny, nz)
+            }),
+        }
+    }
+
+    // Looks up a block by coordinates relative to the chunk, which may lie up to one
+    // block into a face neighbor. Returns `None` for coordinates past an edge or
+    // corner of the chunk and for missing neighbors.
+    fn block_at_signed(&self, pos: [i32; 3]) -> Option<Block> {
+        self.locate_signed(pos)
+            .map(|(chunk, local)| chunk.block_at(local[0], local[1], local[2]))
+    }
+
+    // Blocks outside of the chunk and its neighbors are fully lit
+    fn light_at_signed(&self, pos: [i32; 3]) -> u8 {
+        self.locate_signed(pos)
+            .map(|(chunk, local)| chunk.light_at(local[0], local[1], local[2]))
+            .unwrap_or(MAX_LIGHT)
+    }
+
+    // The chunk containing a position relative to this chunk, and the position within it
+    fn locate_signed(&self, pos: [i32; 3]) -> Option<(&'a S, [usize; 3])> {
+        locate_world::<X, Y, Z, _>(self.chunk, self.neighbors, pos)
+    }
+
+    fn occludes(&self, pos: [i32; 3]) -> bool {
+        self.block_at_signed(pos)
+            .map(|block| block.is_opaque(self.registry))
+            .unwrap_or(false)
+    }
+
+    // Whether blocks of this material are meshed at all
+    fn has_faces(&self, material: MaterialID) -> bool {
+        material != AIR.id && !self.registry.has_custom_model(material)
+    }
+
+    fn is_face_visible(&self, x: usize, y: usize, z: usize, direction: AADirection) -> bool {
+        let block = self.chunk.block_at(x, y, z);
+        if !self.has_faces(block.material) {
+            return false;
+        }
+
+        if !self.config.cull_interior {
+            return true;
+        }
+
+        match self.adjacent_block(x, y, z, direction) {
+            Some(neighbor) => self.registry.draws_face(&block, &neighbor, direction),
+            None => true,
+        }
+    }
+
+    // The blocks in the layer in front of a quad around each of its corners: the one in
+    // front of the quad, the two beside it and the diagonal one. They are found by
+    // stepping away from the center of the quad.
+    fn corner_cells(&self, corners: &[[f32; 3]; 4], direction: AADirection) -> [[[i32; 3]; 4]; 4] {
+        let normal_axis = direction.axis() as usize;
+        let axis_a = (normal_axis + 1) % 3;
+        let axis_b = (normal_axis + 2) % 3;
+
+        let plane = corners[0][normal_axis] as i32;
+        let layer = if direction.is_positive() { plane } else { plane - 1 };
+        let center_a = corners.iter().map(|corner| corner[axis_a]).sum::<f32>() / 4f32;
+        let center_b = corners.iter().map(|corner| corner[axis_b]).sum::<f32>() / 4f32;
+
+        // The block on the quad's side of a corner coordinate, and the one beyond it
+        let around = |coord: f32, center: f32| {
+            let coord = coord as i32;
+            if coord as f32 > center {
+                (coord - 1, coord)
+            } else {
+                (coord, coord - 1)
+            }
+        };
+        let cell = |a: i32, b: i32| {
+            let mut pos = [0i32; 3];
+            pos[normal_axis] = layer;
+            pos[axis_a] = a;
+            pos[axis_b] = b;
+            pos
+        };
+
+        let mut cells = [[[0i32; 3]; 4]; 4];
+        for (corner, corner_cells) in corners.iter().zip(cells.iter_mut()) {
+            let (inner_a, outer_a) = around(corner[axis_a], center_a);
+            let (inner_b, outer_b) = around(corner[axis_b], center_b);
+            *corner_cells = [
+                cell(inner_a, inner_b),
+                cell(outer_a, inner_b),
+                cell(inner_a, outer_b),
+                cell(outer_a, outer_b),
+            ];
+        }
+
+        cells
+    }
+
+    // Occlusion level of each corner of a quad
+    fn ambient_occlusion(&self, cells: &[[[i32; 3]; 4]; 4]) -> [u8; 4] {
+        if !self.config.ambient_occlusion {
+            return [3; 4];
+        }
+
+        let mut occlusion = [3u8; 4];
+        for (corner_cells, level) in cells.iter().zip(occlusion.iter_mut()) {
+            *level = vertex_occlusion(
+                self.occludes(corner_cells[1]),
+                self.occludes(corner_cells[2]),
+                self.occludes(corner_cells[3]),
+            );
+        }
+
+        occlusion
+    }
+
+    // Brightness of each corner of a quad, the average light level of the blocks around
+    // the corner that light can pass through
+    fn corner_light(&self, cells: &[[[i32; 3]; 4]; 4]) -> [f32; 4] {
+        let mut light = [1f32; 4];
+        for (corner_cells, brightness) in cells.iter().zip(light.iter_mut()) {
+            let (sum, count) = corner_cells.iter()
+                .filter(|cell| !self.occludes(**cell))
+                .fold((0u32, 0u32), |(sum, count), cell| (sum + self.light_at_signed(*cell) as u32, count + 1));
+
+            let level = if count > 0 {
+                sum as f32 / count as f32
+            } else {
+                self.light_at_signed(corner_cells[0]) as f32
+            };
+            *brightness = level / MAX_LIGHT as f32;
+        }
+        light
+    }
+
+    // Ambient occlusion and light of the corners of a single block's face
+    fn face_shading(&self, direction: AADirection, block_pos: Point3<usize>) -> ([u8; 4], [f32; 4]) {
+        let corners = face_corners(direction, block_pos, vector![1, 1]);
+        let cells = self.corner_cells(&corners, direction);
+        (self.ambient_occlusion(&cells), self.corner_light(&cells))
+    }
+
+    fn insert_face(
+        &self,
+        direction: AADirection,
+        block_pos: Point3<usize>,
+        dimensions: Vector2<u8>,
+        material: MaterialID,
+        mesh: &mut ChunkMeshData
+    ) {
+        let corners = face_corners(direction, block_pos, dimensions);
+        let cells = self.corner_cells(&corners, direction);
+        let occlusion = self.ambient_occlusion(&cells);
+        let light = self.corner_light(&cells);
+        let uv_rect = self.atlas.rect(material, direction);
+        let tint = self.registry.tint(material);
+
+        let appearance = QuadAppearance { material, uv_rect, tint, occlusion, light };
+
+        insert_quad(corners, dimensions, direction, appearance, mesh);
+    }
+}
+
+// Copies the registered models of custom model blocks into the meshes
+fn append_models<const X: usize, const Y: usize, const Z: usize, S: BlockStorage<X, Y, Z>>(
+    chunk: &S,
+    registry: &MaterialRegistry,
+    buffers: &mut ChunkMeshBuffers
+) {
+    for (x, y, z, block) in storage_blocks(chunk) {
+        if !registry.has_custom_model(block.material) {
+            continue;
+        }
+
+        let model = match registry.model(block.material) {
+            Some(model) => model,
+            None => continue,
+        };
+
+        let mesh = if registry.is_transparent(block.material) {
+            &mut buffers.transparent
+        } else {
+            &mut buffers.opaque
+        };
+
+        append_model(model, base_position(point![x, y, z]), block.material, registry.tint(block.material), mesh);
+    }
+}
+
+fn append_model(model: &Mesh, offset: Point3<f32>, material: MaterialID, tint: [f32; 4], mesh: &mut ChunkMeshData) {
+    let positions = match model.attribute(Mesh::ATTRIBUTE_POSITION) {
+        Some(VertexAttributeValues::Float3(positions)) => positions,
+        _ => return,
+    };
+    let normals = match model.attribute(Mesh::ATTRIBUTE_NORMAL) {
+        Some(VertexAttributeValues::Float3(normals)) => Some(normals),
+        _ => None,
+    };
+    let uvs = match model.attribute(Mesh::ATTRIBUTE_UV_0) {
+        Some(VertexAttributeValues::Float2(uvs)) => Some(uvs),
+        _ => None,
+    };
+
+    let base_vertex_idx = mesh.positions.len() as u32;
+
+    for (i, position) in positions.iter().enumerate() {
+        mesh.positions.push([position[0] + offset.x, position[1] + offset.y, position[2] + offset.z]);
+        mesh.normals.push(normals.map(|normals| normals[i]).unwrap_or([0f32, 1f32, 0f32]));
+        mesh.uvs.push(uvs.map(|uvs| uvs[i]).unwrap_or([0f32, 0f32]));
+        mesh.colors.push(tint);
+        mesh.materials.push(material);
+    }
+
+    match model.indices() {
+        Some(Indices::U16(indices)) => {
+            mesh.indices.extend(indices.iter().map(|index| base_vertex_idx + *index as u32));
+        },
+        Some(Indices::U32(indices)) => {
+            mesh.indices.extend(indices.iter().map(|index| base_vertex_idx + index));
+        },
+        None => {
+            mesh.indices.extend((0..positions.len() as u32).map(|index| base_vertex_idx + index));
+        },
+    }
+}
+
+fn collect_custom_models<const X: usize, const Y: usize, const Z: usize, S: BlockStorage<X, Y, Z>>(
+    chunk: &S,
+    registry: &MaterialRegistry
+) -> Vec<(Point3<usize>, MaterialID)> {
+    let size = chunk.size();
+    let mut custom_models = Vec::new();
+
+    for z in 0..size.z {
+        for y in 0..size.y {
+            for x in 0..size.x {
+                let material = chunk.block_at(x, y, z).material;
+                if registry.has_custom_model(material) && registry.model(material).is_none() {
+                    custom_models.push((point![x, y, z], material));
+                }
+            }
+        }
+    }
+
+    custom_models
+}
+
+pub struct NaiveChunkMesher {}
+
+impl ChunkMesher for NaiveChunkMesher {
+
+    fn config() -> MeshConfig {
+        NAIVE_CONFIG
+    }
+
+    fn generate_mesh<const X: usize, const Y: usize, const Z: usize, S: BlockStorage<X, Y, Z>>(
+        chunk: &S,
+        neighbors: &ChunkNeighborhood<X, Y, Z, S>,
+        registry: &MaterialRegistry,
+        atlas: &MaterialAtlas
+    ) -> Result<ChunkMeshes, MeshError> {
+        generate_mesh_with(chunk, neighbors, registry, atlas, &NAIVE_CONFIG)
+    }
+
+    fn generate_mesh_lod<const X: usize, const Y: usize, const Z: usize, S: BlockStorage<X, Y, Z>>(
+        chunk: &S,
+        neighbors: &ChunkNeighborhood<X, Y, Z, S>,
+        registry: &MaterialRegistry,
+        atlas: &MaterialAtlas,
+        lod: u8,
+    ) -> Result<ChunkMeshes, MeshError> {
+        let config = MeshConfig {
+            lod,
+            ..NAIVE_CONFIG
+        };
+        generate_mesh_with(chunk, neighbors, registry, atlas, &config)
+    }
+
+    fn generate_mesh_with_buffers<const X: usize, const Y: usize, const Z: usize, S: BlockStorage<X, Y, Z>>(
+        chunk: &S,
+        neighbors: &ChunkNeighborhood<X, Y, Z, S>,
+        registry: &MaterialRegistry,
+        atlas: &MaterialAtlas,
+        buffers: &mut ChunkMeshBuffers,
+    ) -> Result<ChunkMeshes, MeshError> {
+        generate_mesh_with_buffers(chunk, neighbors, registry, atlas, &NAIVE_CONFIG, buffers)
+    }
+}
+
+const NAIVE_CONFIG : MeshConfig = MeshConfig {
+    greedy : false,
+    ..MeshConfig::DEFAULT
+};
+
+fn generate_naive_mesh<const X: usize, const Y: usize, const Z: usize, S: BlockStorage<X, Y, Z>>(
+    context: &MeshingContext<X, Y, Z, S>,
+    buffers: &mut ChunkMeshBuffers
+) -> usize {
+    generate_naive_region(context, point![0, 0, 0], context.chunk.size().into(), buffers, |_, _, _| {})
+}
+
+// Meshes the blocks from `min` inclusive to `max` exclusive. `on_block` is called with
+// each block that got faces, whether they went into the transparent mesh and how many
+// quads were added.
+fn generate_naive_region<const X: usize, const Y: usize, const Z: usize, S: BlockStorage<X, Y, Z>>(
+    context: &MeshingContext<X, Y, Z, S>,
+    min: Point3<usize>,
+    max: Point3<usize>,
+    buffers: &mut ChunkMeshBuffers,
+    mut on_block: impl FnMut(Point3<usize>, bool, usize),
+) -> usize {
+    const DIMENSIONS : Vector2<u8> = vector![1, 1];
+
+    let ChunkMeshBuffers { opaque, transparent } = buffers;
+    let mut faces_culled = 0;
+
+    for z in min.z..max.z {
+        for y in min.y..max.y {
+            for x in min.x..max.x {
+
+                let block_pos = point![x, y, z];
+                let material = context.chunk.block_at(x, y, z).material;
+                if !context.has_faces(material) {
+                    continue;
+                }
+
+                let is_transparent = context.registry.is_transparent(material);
+                let mesh = if is_transparent {
+                    &mut *transparent
+                } else {
+                    &mut *opaque
+                };
+                let vertices_before = mesh.vertex_count();
+
+                for direction in AADirection::ALL.iter() {
+                    if context.is_face_visible(x, y, z, *direction) {
+                        context.insert_face(*direction, block_pos, DIMENSIONS, material, mesh);
+                    } else {
+                        faces_culled += 1;
+                    }
+                }
+
+                let quads = (mesh.vertex_count() - vertices_before) / 4;
+                if quads > 0 {
+                    on_block(block_pos, is_transparent, quads);
+                }
+            }
+        }
+    }
+
+    faces_culled
+}
+
+// A naively meshed chunk that can be brought up to date after block edits by remeshing
+// only the blocks around `Chunk::dirty_region`. Each quad remembers the block it belongs
+// to, so the quads of the remeshed blocks can be removed and emitted again while the rest
+// of the mesh stays as it is. Custom models aren't meshed.
+// Culling, ambient occlusion, tangents, the winding and the offset of the `MeshConfig` are
+// applied to the remeshed quads, so every call has to get the same config. Greedy meshing,
+// LOD, smooth normals and welding combine the faces of several blocks and aren't supported.
+// They have to be turned off, which is checked in debug builds.
+pub struct IncrementalChunkMesh {
+    buffers : ChunkMeshBuffers,
+    // Block of every quad, in the order of the quads in the buffers
+    opaque_blocks : Vec<Point3<usize>>,
+    transparent_blocks : Vec<Point3<usize>>,
+    // The remeshed quads, before they are added to `buffers`
+    scratch : ChunkMeshBuffers,
+}
+
+impl IncrementalChunkMesh {
+
+    pub fn new<const X: usize, const Y: usize, const Z: usize>(
+        chunk: &Chunk<X, Y, Z>,
+        neighbors: &ChunkNeighborhood<X, Y, Z>,
+        registry: &MaterialRegistry,
+        atlas: &MaterialAtlas,
+        config: &MeshConfig,
+    ) -> Result<Self, MeshError> {
+        let mut mesh = IncrementalChunkMesh {
+            buffers : ChunkMeshBuffers::new(),
+            opaque_blocks : Vec::new(),
+            transparent_blocks : Vec::new(),
+            scratch : ChunkMeshBuffers::new(),
+        };
+        let context = MeshingContext::validated(chunk, neighbors, registry, atlas, config)?;
+        mesh.remesh_region(&context, point![0, 0, 0], point![X, Y, Z]);
+        Ok(mesh)
+    }
+
+    // Remeshes the blocks in the chunk's dirty region and the blocks next to it, whose
+    // culling and ambient occlusion may depend on the modified blocks. Does nothing if
+    // no blocks were modified.
+    pub fn update<const X: usize, const Y: usize, const Z: usize>(
+        &mut self,
+        chunk: &Chunk<X, Y, Z>,
+        neighbors: &ChunkNeighborhood<X, Y, Z>,
+        registry: &MaterialRegistry,
+        atlas: &MaterialAtlas,
+        config: &MeshConfig,
+    ) -> Result<(), MeshError> {
+        let (min, max) = match chunk.dirty_region() {
+            Some(region) => region,
+            None => return Ok(()),
+        };
+
+        let min = point![min.x.saturating_sub(1), min.y.saturating_sub(1), min.z.saturating_sub(1)];
+        let max = point![(max.x + 2).min(X), (max.y + 2).min(Y), (max.z + 2).min(Z)];
+
+        let context = MeshingContext::validated(chunk, neighbors, registry, atlas, config)?;
+        self.remesh_region(&context, min, max);
+        Ok(())
+    }
+
+    pub fn opaque(&self) -> &ChunkMeshData {
+        &self.buffers.opaque
+    }
+
+    pub fn transparent(&self) -> &ChunkMeshData {
+        &self.buffers.transparent
+    }
+
+    pub fn to_meshes(&self) -> ChunkMeshes {
+        ChunkMeshes::new(
+            self.buffers.opaque.clone(),
+            self.buffers.transparent.clone(),
+            Vec::new(),
+            self.opaque_blocks.len() + self.transparent_blocks.len(),
+            0,
+            &MeshConfig::default()
+        )
+    }
+
+    // `max` is exclusive
+    fn remesh_region<const X: usize, const Y: usize, const Z: usize>(
+        &mut self,
+        context: &MeshingContext<X, Y, Z>,
+        min: Point3<usize>,
+        max: Point3<usize>,
+    ) {
+        let config = context.config;
+        debug_assert!(
+            !config.greedy && config.lod == 0 && !config.smooth_normals && !config.weld_vertices,
+            "IncrementalChunkMesh doesn't support greedy meshing, LOD, smooth normals or welding"
+        );
+
+        let in_region = |block: &Point3<usize>| {
+            (0..3).all(|axis| block[axis] >= min[axis] && block[axis] < max[axis])
+        };
+
+        let opaque_blocks = &self.opaque_blocks;
+        self.buffers.opaque.retain_quads(|quad| !in_region(&opaque_blocks[quad]));
+        self.opaque_blocks.retain(|block| !in_region(block));
+
+        let transparent_blocks = &self.transparent_blocks;
+        self.buffers.transparent.retain_quads(|quad| !in_region(&transparent_blocks[quad]));
+        self.transparent_blocks.retain(|block| !in_region(block));
+
+        let IncrementalChunkMesh { buffers, opaque_blocks, transparent_blocks, scratch } = self;
+
+        scratch.clear();
+        generate_naive_region(context, min, max, scratch, |block, is_transparent, quads| {
+            let blocks = if is_transparent { &mut *transparent_blocks } else { &mut *opaque_blocks };
+            blocks.extend(std::iter::repeat_n(block, quads));
+        });
+
+        for (mesh, remeshed) in [(&mut buffers.opaque, &mut scratch.opaque), (&mut buffers.transparent, &mut scratch.transparent)] {
+            if config.winding == Winding::Clockwise {
+                remeshed.flip_winding();
+            }
+            if config.tangents {
+                remeshed.generate_tangents();
+            }
+            remeshed.translate(config.offset);
+            mesh.append(remeshed, Vector3::zeros());
+        }
+    }
+}
+
+// Number of slices along the face normal, followed by the extent of the face
+// plane along u and v (see the face inserters for which axes those are).
+fn plane_size(size: Vector3<usize>, direction: AADirection) -> (usize, usize, usize) {
+    match direction.axis() {
+        Axis::X => (size.x, size.z, size.y),
+        Axis::Y => (size.y, size.x, size.z),
+        Axis::Z => (size.z, size.x, size.y),
+    }
+}
+
+// The axes along u and v of the planes of faces facing `direction`
+fn plane_axes(direction: AADirection) -> (Axis, Axis) {
+    match direction.axis() {
+        Axis::X => (Axis::Z, Axis::Y),
+        Axis::Y => (Axis::X, Axis::Z),
+        Axis::Z => (Axis::X, Axis::Y),
+    }
+}
+
+// Whether `sweep_order` prefers merging along v over u in the planes facing `direction`
+fn sweeps_v_first(direction: AADirection, sweep_order: &[Axis; 3]) -> bool {
+    let (u, v) = plane_axes(direction);
+    let rank = |axis: Axis| sweep_order.iter().position(|entry| *entry == axis).unwrap_or(3);
+    rank(v) < rank(u)
+}
+
+fn plane_to_block(
+    direction: AADirection,
+    slice: usize,
+    u: usize,
+    v: usize
+) -> (usize, usize, usize) {
+    match direction.axis() {
+        Axis::X => (slice, v, u),
+        Axis::Y => (u, slice, v),
+        Axis::Z => (u, v, slice),
+    }
+}
+
+// Greedily merges equal cells of a face mask into rectangles, clearing the mask in the
+// process. Calls `emit` with the position of each rectangle's first cell, its width
+// along u, its height along v, and the value it covers. Neither side grows past
+// `max_extent` cells.
+fn merge_plane<T: Copy + PartialEq>(
+    mask: &mut [Option<T>],
+    size_u: usize,
+    size_v: usize,
+    max_extent: usize,
+    mut emit: impl FnMut(usize, usize, usize, usize, T)
+) {
+    for v in 0..size_v {
+        let mut u = 0;
+        while u < size_u {
+
+            let value = match mask[v * size_u + u] {
+                Some(value) => value,
+                None => {
+                    u += 1;
+                    continue;
+                }
+            };
+
+            let mut width = 1;
+            while width < max_extent && u + width < size_u && mask[v * size_u + u + width] == Some(value) {
+                width += 1;
+            }
+
+            let mut height = 1;
+            'grow: while height < max_extent && v + height < size_v {
+                for du in 0..width {
+                    if mask[(v + height) * size_u + u + du] != Some(value) {
+                        break 'grow;
+                    }
+                }
+                height += 1;
+            }
+
+            for dv in 0..height {
+                for du in 0..width {
+                    mask[(v + dv) * size_u + u + du] = None;
+                }
+            }
+
+            emit(u, v, width, height, value);
+
+            u += width;
+        }
+    }
+}
+
+pub struct GreedyChunkMesher {}
+
+// What two faces of a plane need to have in common to be merged
+#[derive(PartialEq, Copy, Clone)]
+struct GreedyFace {
+    material: MaterialID,
+    occlusion: [u8; 4],
+    light: [f32; 4],
+}
+
+impl ChunkMesher for GreedyChunkMesher {
+
+    fn generate_mesh<const X: usize, const Y: usize, const Z: usize, S: BlockStorage<X, Y, Z>>(
+        chunk: &S,
+        neighbors: &ChunkNeighborhood<X, Y, Z, S>,
+        registry: &MaterialRegistry,
+        atlas: &MaterialAtlas
+    ) -> Result<ChunkMeshes, MeshError> {
+        generate_mesh_with(chunk, neighbors, registry, atlas, &MeshConfig::default())
+    }
+
+    fn generate_mesh_with_buffers<const X: usize, const Y: usize, const Z: usize, S: BlockStorage<X, Y, Z>>(
+        chunk: &S,
+        neighbors: &ChunkNeighborhood<X, Y, Z, S>,
+        registry: &MaterialRegistry,
+        atlas: &MaterialAtlas,
+        buffers: &mut ChunkMeshBuffers,
+    ) -> Result<ChunkMeshes, MeshError> {
+        generate_mesh_with_buffers(chunk, neighbors, registry, atlas, &MeshConfig::default(), buffers)
+    }
+}
+
+// A merged face as found by greedy meshing, before its vertices are generated. `size` is
+// the extent along the u and v axes of the face's plane, in blocks.
+#[derive(Eq, PartialEq, Debug, Copy, Clone)]
+pub struct FaceQuad {
+    pub pos : Point3<usize>,
+    pub dir : AADirection,
+    pub size : Vector2<u8>,
+    pub material : MaterialID,
+}
+
+// The quads `GreedyChunkMesher` would emit for the chunk with the given config, in order
+pub fn greedy_face_quads<const X: usize, const Y: usize, const Z: usize, S: BlockStorage<X, Y, Z>>(
+    chunk: &S,
+    neighbors: &ChunkNeighborhood<X, Y, Z, S>,
+    registry: &MaterialRegistry,
+    config: &MeshConfig,
+) -> Result<Vec<FaceQuad>, MeshError> {
+    let atlas = MaterialAtlas::default();
+    let context = MeshingContext::validated(chunk, neighbors, registry, &atlas, config)?;
+    let mut quads = Vec::new();
+    collect_greedy_quads(&context, &mut quads);
+    Ok(quads)
+}
+
+fn generate_greedy_mesh<const X: usize, const Y: usize, const Z: usize, S: BlockStorage<X, Y, Z>>(
+    context: &MeshingContext<X, Y, Z, S>,
+    buffers: &mut ChunkMeshBuffers
+) -> usize {
+    let mut quads = Vec::new();
+    let faces_culled = collect_greedy_quads(context, &mut quads);
+
+    let ChunkMeshBuffers { opaque, transparent } = buffers;
+    for quad in quads {
+        let mesh = if context.registry.is_transparent(quad.material) {
+            &mut *transparent
+        } else {
+            &mut *opaque
+        };
+        context.insert_face(quad.dir, quad.pos, quad.size, quad.material, mesh);
+    }
+
+    faces_culled
+}
+
+// Merges the visible faces of each plane into quads. Returns the number of culled faces.
+fn collect_greedy_quads<const X: usize, const Y: usize, const Z: usize, S: BlockStorage<X, Y, Z>>(
+    context: &MeshingContext<X, Y, Z, S>,
+    quads: &mut Vec<FaceQuad>
+) -> usize {
+    let max_extent = context.config.max_quad_size.max(1) as usize;
+    let mut faces_culled = 0;
+
+    for direction in AADirection::ALL.iter() {
+
+        let (slices, size_u, size_v) = plane_size(context.chunk.size(), *direction);
+        // The mask is transposed to merge along v first
+        let transposed = sweeps_v_first(*direction, &context.config.sweep_order);
+        let (mask_u, mask_v) = if transposed { (size_v, size_u) } else { (size_u, size_v) };
+        // Faces are only merged if their corners are shaded the same, otherwise the
+        // shading of the merged quad would be stretched across faces it doesn't belong to
+        let mut mask : Vec<Option<GreedyFace>> = vec![None; size_u * size_v];
+
+        for slice in 0..slices {
+
+            for v in 0..size_v {
+                for u in 0..size_u {
+                    let (x, y, z) = plane_to_block(*direction, slice, u, v);
+                    let material = context.chunk.block_at(x, y, z).material;
+                    let cell = if transposed { u * mask_u + v } else { v * mask_u + u };
+                    mask[cell] = if context.is_face_visible(x, y, z, *direction) {
+                        let (occlusion, light) = context.face_shading(*direction, point![x, y, z]);
+                        Some(GreedyFace { material, occlusion, light })
+                    } else {
+                        if context.has_faces(material) {
+                            faces_culled += 1;
+                        }
+                        None
+                    };
+                }
+            }
+
+            merge_plane(&mut mask, mask_u, mask_v, max_extent, |a, b, extent_a, extent_b, GreedyFace { material, .. }| {
+                let (u, v, width, height) = if transposed {
+                    (b, a, extent_b, extent_a)
+                } else {
+                    (a, b, extent_a, extent_b)
+                };
+                let (x, y, z) = plane_to_block(*direction, slice, u, v);
+                quads.push(FaceQuad {
+                    pos : point![x, y, z],
+                    dir : *direction,
+                    size : vector![width as u8, height as u8],
+                    material,
+                });
+            });
+        }
+    }
+
+    faces_culled
+}
+
+// Keeps the meshes of the chunk states it saw last, so that chunks that return to an earlier
+// state, like after an undo, aren't meshed again. A state is made up of the chunk's blocks
+// and light and the neighbors' layers facing the chunk. States are looked up by their hash
+// and compared in full, so every cached state keeps a copy of them. The registry and atlas
+// aren't part of it, so clear the cache when they change.
+pub struct ChunkMeshCache<Mesher: ChunkMesher> {
+    capacity : usize,
+    entries : HashMap<u64, CacheEntry>,
+    // Ends of the list of entries from the least to the most recently used
+    oldest : Option<u64>,
+    newest : Option<u64>,
+    hits : usize,
+    misses : usize,
+    mesher : PhantomData<fn() -> Mesher>,
+}
+
+struct CacheEntry {
+    content : ChunkContent,
+    meshes : ChunkMeshes,
+    // The keys of the entries used right before and after this one
+    older : Option<u64>,
+    newer : Option<u64>,
+}
+
+impl<Mesher: ChunkMesher> ChunkMeshCache<Mesher> {
+
+    // Keeps the meshes of at most `capacity` chunk states
+    pub fn new(capacity : usize) -> Self {
+        ChunkMeshCache {
+            capacity,
+            entries : HashMap::new(),
+            oldest : None,
+            newest : None,
+            hits : 0,
+            misses : 0,
+            mesher : PhantomData,
+        }
+    }
+
+    // Returns a copy of the cached meshes if the chunk was meshed in the same state before.
+    // Failed meshing isn't cached.
+    pub fn generate_mesh<const X: usize, const Y: usize, const Z: usize, S: BlockStorage<X, Y, Z>>(
+        &mut self,
+        chunk : &S,
+        neighbors : &ChunkNeighborhood<X, Y, Z, S>,
+        registry : &MaterialRegistry,
+        atlas : &MaterialAtlas,
+    ) -> Result<ChunkMeshes, MeshError> {
+        let content = ChunkContent::new::<X, Y, Z, S>(chunk, neighbors);
+        let key = content.hash_key();
+
+        if let Some(meshes) = self.get(key, &content) {
+            return Ok(meshes);
+        }
+
+        let meshes = Mesher::generate_mesh(chunk, neighbors, registry, atlas)?;
+        self.insert(key, content, meshes.clone());
+        Ok(meshes)
+    }
+
+    // Counts the lookup as a hit or a miss
+    fn get(&mut self, key : u64, content : &ChunkContent) -> Option<ChunkMeshes> {
+        let meshes = match self.entries.get(&key) {
+            Some(entry) if entry.content == *content => entry.meshes.clone(),
+            _ => {
+                self.misses += 1;
+                return None;
+            },
+        };
+
+        self.hits += 1;
+        self.unlink(key);
+        self.push_newest(key);
+        Some(meshes)
+    }
+
+    fn insert(&mut self, key : u64, content : ChunkContent, meshes : ChunkMeshes) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        // Another state with the same hash is replaced
+        self.remove(key);
+        while self.entries.len() >= self.capacity {
+            match self.oldest {
+                Some(oldest) => self.remove(oldest),
+                None => break,
+            }
+        }
+
+        self.entries.insert(key, CacheEntry { content, meshes, older : None, newer : None });
+        self.push_newest(key);
+    }
+
+    fn remove(&mut self, key : u64) {
+        self.unlink(key);
+        self.entries.remove(&key);
+    }
+
+    // Takes the entry out of the list of entries
+    fn unlink(&mut self, key : u64) {
+        let (older, newer) = match self.entries.get_mut(&key) {
+            Some(entry) => (entry.older.take(), entry.newer.take()),
+            None => return,
+        };
+
+        match older.and_then(|older| self.entries.get_mut(&older)) {
+            Some(entry) => entry.newer = newer,
+            None => self.newest = if self.newest == Some(key) { older } else { self.newest },
+        }
+        match newer.and_then(|newer| self.entries.get_mut(&newer)) {
+            Some(entry) => entry.older = older,
+            None => self.oldest = if self.oldest == Some(key) { newer } else { self.oldest },
+        }
+    }
+
+    // Appends an unlinked entry to the end of the list of entries
+    fn push_newest(&mut self, key : u64) {
+        match self.newest.and_then(|newest| self.entries.get_mut(&newest)) {
+            Some(entry) => entry.newer = Some(key),
+            None => self.oldest = Some(key),
+        }
+        if let Some(entry) = self.entries.get_mut(&key) {
+            entry.older = self.newest;
+        }
+        self.newest = Some(key);
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+        self.oldest = None;
+        self.newest = None;
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    // Number of lookups that were answered from the cache
+    pub fn hits(&self) -> usize {
+        self.hits
+    }
+
+    // Number of lookups that had to run the mesher
+    pub fn misses(&self) -> usize {
+        self.misses
+    }
+}
+
+// Wraps a mesher with a `ChunkMeshCache` of `CAPACITY` states, e.g. to use it as the mesher
+// of the meshing systems. Meshers don't keep anything between calls, so the cache is shared
+// by every user of the same mesher type and capacity, across all threads. Only full
+// resolution meshes are cached.
+pub struct CachingChunkMesher<Mesher: ChunkMesher, const CAPACITY: usize = 64> {
+    mesher : PhantomData<fn() -> Mesher>,
+}
+
+impl<Mesher: ChunkMesher + 'static, const CAPACITY: usize> CachingChunkMesher<Mesher, CAPACITY> {
+
+    // Runs `with` on the shared cache, e.g. to clear it when the registry or atlas change.
+    // The meshers run outside of it, so meshing in parallel only waits for the lookups.
+    pub fn with_cache<T>(with: impl FnOnce(&mut ChunkMeshCache<Mesher>) -> T) -> T {
+        type Caches = Mutex<HashMap<(TypeId, usize), Box<dyn Any + Send>>>;
+        static CACHES : OnceLock<Caches> = OnceLock::new();
+
+        let mut caches = CACHES.get_or_init(Default::default)
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        let cache = caches.entry((TypeId::of::<Mesher>(), CAPACITY))
+            .or_insert_with(|| Box::new(ChunkMeshCache::<Mesher>::new(CAPACITY)))
+            .downcast_mut::<ChunkMeshCache<Mesher>>()
+            .expect("Caches are keyed by their mesher type");
+        with(cache)
+    }
+
+    fn generate_cached<const X: usize, const Y: usize, const Z: usize, S: BlockStorage<X, Y, Z>>(
+        chunk: &S,
+        neighbors: &ChunkNeighborhood<X, Y, Z, S>,
+        generate: impl FnOnce() -> Result<ChunkMeshes, MeshError>
+    ) -> Result<ChunkMeshes, MeshError> {
+        let content = ChunkContent::new::<X, Y, Z, S>(chunk, neighbors);
+        let key = content.hash_key();
+
+        if let Some(meshes) = Self::with_cache(|cache| cache.get(key, &content)) {
+            return Ok(meshes);
+        }
+
+        let meshes = generate()?;
+        Self::with_cache(|cache| cache.insert(key, content, meshes.clone()));
+        Ok(meshes)
+    }
+}
+
+impl<Mesher: ChunkMesher + 'static, const CAPACITY: usize> ChunkMesher for CachingChunkMesher<Mesher, CAPACITY> {
+
+    fn config() -> MeshConfig {
+        Mesher::config()
+    }
+
+    fn generate_mesh<const X: usize, const Y: usize, const Z: usize, S: BlockStorage<X, Y, Z>>(
+        chunk: &S,
+        neighbors: &ChunkNeighborhood<X, Y, Z, S>,
+        registry: &MaterialRegistry,
+        atlas: &MaterialAtlas
+    ) -> Result<ChunkMeshes, MeshError> {
+        Self::generate_cached(chunk, neighbors, || Mesher::generate_mesh(chunk, neighbors, registry, atlas))
+    }
+
+    fn generate_mesh_lod<const X: usize, const Y: usize, const Z: usize, S: BlockStorage<X, Y, Z>>(
+        chunk: &S,
+        neighbors: &ChunkNeighborhood<X, Y, Z, S>,
+        registry: &MaterialRegistry,
+        atlas: &MaterialAtlas,
+        lod: u8,
+    ) -> Result<ChunkMeshes, MeshError> {
+        if lod == 0 {
+            Self::generate_mesh(chunk, neighbors, registry, atlas)
+        } else {
+            Mesher::generate_mesh_lod(chunk, neighbors, registry, atlas, lod)
+        }
+    }
+
+    fn generate_mesh_with_buffers<const X: usize, const Y: usize, const Z: usize, S: BlockStorage<X, Y, Z>>(
+        chunk: &S,
+        neighbors: &ChunkNeighborhood<X, Y, Z, S>,
+        registry: &MaterialRegistry,
+        atlas: &MaterialAtlas,
+        buffers: &mut ChunkMeshBuffers,
+    ) -> Result<ChunkMeshes, MeshError> {
+        Self::generate_cached(chunk, neighbors, || Mesher::generate_mesh_with_buffers(chunk, neighbors, registry, atlas, buffers))
+    }
+}
+
+// Everything the meshes of a chunk are built from, apart from the registry and atlas: the
+// material and light of its blocks, and of the layer of each neighbor that faces it
+#[derive(Eq, PartialEq, Hash)]
+struct ChunkContent {
+    blocks : Vec<(MaterialID, u8)>,
+    neighbor_layers : [Option<Vec<(MaterialID, u8)>>; 6],
+}
+
+impl ChunkContent {
+
+    fn new<const X: usize, const Y: usize, const Z: usize, S: BlockStorage<X, Y, Z>>(
+        chunk : &S,
+        neighbors : &ChunkNeighborhood<X, Y, Z, S>
+    ) -> Self {
+        let blocks = storage_blocks(chunk)
+            .map(|(x, y, z, block)| (block.material, chunk.light_at(x, y, z)))
+            .collect();
+
+        let mut neighbor_layers : [Option<Vec<(MaterialID, u8)>>; 6] = Default::default();
+        for (direction, neighbor) in AADirection::ALL.iter().zip(neighbors.iter()) {
+            let neighbor = match neighbor {
+                Some(neighbor) => neighbor,
+                None => continue,
+            };
+
+            let facing = direction.opposite();
+            let (slices, size_u, size_v) = plane_size(neighbor.size(), facing);
+            let slice = if facing.is_positive() { slices - 1 } else { 0 };
+            let mut layer = Vec::with_capacity(size_u * size_v);
+            for v in 0..size_v {
+                for u in 0..size_u {
+                    let (x, y, z) = plane_to_block(facing, slice, u, v);
+                    layer.push((neighbor.block_at(x, y, z).material, neighbor.light_at(x, y, z)));
+                }
+            }
+            neighbor_layers[*direction as usize] = Some(layer);
+        }
+
+        ChunkContent { blocks, neighbor_layers }
+    }
+
+    fn hash_key(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.hash(&mut hasher);
+        hasher.finish()
+    }
+}
+
+// A chunk downsampled for level of detail meshing. Every cube of `step` blocks along
+// each axis becomes one cell, which takes the material of the first block in it that
+// isn't air. The cells on the far sides cover fewer blocks if the chunk size isn't a
+// multiple of `step`.
+struct CoarseBlocks {
+    size : Vector3<usize>,
+    blocks : Vec<Block>,
+}
+
+impl CoarseBlocks {
+
+    fn new<const X: usize, const Y: usize, const Z: usize, S: BlockStorage<X, Y, Z>>(chunk : &S, step : usize) -> Self {
+        Self::with_cells(chunk, step, |_| true)
+    }
+
+    // Only fills in the layer of cells touching the chunk on whose `side` the neighbor
+    // lies, which are the only cells of a neighbor the meshers look at. The other cells
+    // are left as air.
+    fn facing_layer<const X: usize, const Y: usize, const Z: usize, S: BlockStorage<X, Y, Z>>(
+        neighbor : &S,
+        step : usize,
+        side : AADirection
+    ) -> Self {
+        let axis = side.axis() as usize;
+        let layer = if side.is_positive() { 0 } else { neighbor.size()[axis].div_ceil(step) - 1 };
+        Self::with_cells(neighbor, step, |cell| cell[axis] == layer)
+    }
+
+    fn with_cells<const X: usize, const Y: usize, const Z: usize, S: BlockStorage<X, Y, Z>>(
+        chunk : &S,
+        step : usize,
+        fill : impl Fn(Vector3<usize>) -> bool
+    ) -> Self {
+        let source = chunk.size();
+        let size = source.map(|extent| extent.div_ceil(step));
+        let mut blocks = vec![Block::default(); size.x * size.y * size.z];
+
+        for (index, block) in blocks.iter_mut().enumerate() {
+            let cell = vector![index % size.x, (index / size.x) % size.y, index / (size.x * size.y)];
+            if !fill(cell) {
+                continue;
+            }
+
+            let min = cell * step;
+            let max = (min + vector![step, step, step]).zip_map(&source, usize::min);
+            'cell: for z in min.z..max.z {
+                for y in min.y..max.y {
+                    for x in min.x..max.x {
+                        let candidate = chunk.block_at(x, y, z);
+                        if !candidate.is_air() {
+                            *block = candidate;
+                            break 'cell;
+                        }
+                    }
+                }
+            }
+        }
+
+        CoarseBlocks { size, blocks }
+    }
+}
+
+impl<const X: usize, const Y: usize, const Z: usize> BlockStorage<X, Y, Z> for CoarseBlocks {
+
+    fn block_at(&self, x : usize, y : usize, z : usize) -> Block {
+        self.blocks[self.size.x * (self.size.y * z + y) + x].clone()
+    }
+
+    fn size(&self) -> Vector3<usize> {
+        self.size
+    }
+}
+
+// Meshes the chunk of the context downsampled by 2^lod, with the same mesher and options
+// as full resolution meshes apart from ambient occlusion, then scales the result back up
+// to the size of the chunk. The neighbors are only downsampled in the layer facing the
+// chunk, which is all the meshers look at. Coarse faces tile their texture once per cell.
+fn generate_coarse_mesh<const X: usize, const Y: usize, const Z: usize, S: BlockStorage<X, Y, Z>>(
+    context: &MeshingContext<X, Y, Z, S>,
+    buffers: &mut ChunkMeshBuffers
+) -> usize {
+    // Coarse faces have to fit into the u8 face dimensions
+    let step = 1usize << context.config.lod.min(7);
+
+    let chunk = CoarseBlocks::new(context.chunk, step);
+    let layers : Vec<Option<CoarseBlocks>> = AADirection::ALL.iter()
+        .map(|direction| context.neighbors[*direction as usize].map(|neighbor| CoarseBlocks::facing_layer(neighbor, step, *direction)))
+        .collect();
+    let mut neighbors : ChunkNeighborhood<X, Y, Z, CoarseBlocks> = [None; 6];
+    for (neighbor, layer) in neighbors.iter_mut().zip(layers.iter()) {
+        *neighbor = layer.as_ref();
+    }
+
+    let config = MeshConfig {
+        cull_interior : true,
+        ambient_occlusion : false,
+        lod : 0,
+        ..*context.config
+    };
+    let coarse : MeshingContext<X, Y, Z, CoarseBlocks> = MeshingContext {
+        chunk : &chunk,
+        neighbors : &neighbors,
+        registry : context.registry,
+        atlas : context.atlas,
+        config : &config,
+    };
+    let faces_culled = if config.greedy {
+        generate_greedy_mesh(&coarse, buffers)
+    } else {
+        generate_naive_mesh(&coarse, buffers)
+    };
+
+    let size = context.chunk.size();
+    for mesh in [&mut buffers.opaque, &mut buffers.transparent].iter_mut() {
+        for position in mesh.positions.iter_mut() {
+            for axis in 0..3 {
+                position[axis] = (position[axis] * step as f32).min(size[axis] as f32);
+            }
+        }
+    }
+
+    faces_culled
+}
+
+// Builds a collider for the solid blocks of a chunk. Faces are greedily merged regardless
+// of material and the mesh only has positions and indices. Faces towards solid blocks in
+// the neighboring chunks are left out so the collider has no internal walls.
+pub fn generate_collision_mesh<const X: usize, const Y: usize, const Z: usize, S: BlockStorage<X, Y, Z>>(
+    chunk: &S,
+    neighbors: &ChunkNeighborhood<X, Y, Z, S>,
+    registry: &MaterialRegistry
+) -> Mesh {
+    let size = chunk.size();
+    let mut positions = Vec::new();
+    let mut indices = Vec::new();
+
+    for direction in AADirection::ALL.iter() {
+
+        let (slices, size_u, size_v) = plane_size(size, *direction);
+        let mut mask : Vec<Option<()>> = vec![None; size_u * size_v];
+
+        for slice in 0..slices {
+
+            for v in 0..size_v {
+                for u in 0..size_u {
+                    let (x, y, z) = plane_to_block(*direction, slice, u, v);
+                    let adjacent = match adjacent_in_chunk(size, x, y, z, *direction) {
+                        Some((nx, ny, nz)) => Some(chunk.block_at(nx, ny, nz)),
+                        None => neighbors[*direction as usize].map(|neighbor| {
+                            let (nx, ny, nz) = adjacent_in_neighbor(size, x, y, z, *direction);
+                            neighbor.block_at(nx, ny, nz)
+                        }),
+                    };
+
+                    let exposed = chunk.block_at(x, y, z).is_opaque(registry)
+                        && !adjacent.map(|block| block.is_opaque(registry)).unwrap_or(false);
+                    mask[v * size_u + u] = if exposed { Some(()) } else { None };
+                }
+            }
+
+            merge_plane(&mut mask, size_u, size_v, u8::MAX as usize, |u, v, width, height, _| {
+                let (x, y, z) = plane_to_block(*direction, slice, u, v);
+                let base_vertex_idx = positions.len() as u32;
+
+                positions.extend_from_slice(&face_corners(*direction, point![x, y, z], vector![width as u8, height as u8]));
+                indices.extend([0, 1, 2, 2, 3, 0].iter().map(|index| base_vertex_idx + index));
+            });
+        }
+    }
+
+    let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+    mesh.set_indices(Some(compact_indices(positions.len(), indices)));
+    mesh.set_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+
+    mesh
+}
+
+// Shows the contents of a chunk before it can be meshed: a `PointList` mesh with a point at
+// the center of every block that isn't air, colored by its material
+pub fn generate_point_cloud<const X: usize, const Y: usize, const Z: usize, S: BlockStorage<X, Y, Z>>(
+    chunk: &S
+) -> Mesh {
+    let mut positions = Vec::new();
+    let mut colors = Vec::new();
+
+    for (x, y, z, block) in storage_blocks(chunk) {
+        if block.is_air() {
+            continue;
+        }
+        positions.push([x as f32 + 0.5, y as f32 + 0.5, z as f32 + 0.5]);
+        colors.push(debug_color(block.material));
+    }
+
+    let mut mesh = Mesh::new(PrimitiveTopology::PointList);
+    mesh.set_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+    mesh.set_attribute(Mesh::ATTRIBUTE_COLOR, colors);
+
+    mesh
+}
+
+// A bright color that is the same for every block of a material
+fn debug_color(material: MaterialID) -> [f32; 4] {
+    debug_index_color(material.id())
+}
+
+// A bright color for the `index`-th of a series, like the triangles of a mesh. The hues of
+// consecutive indices are spread by the golden ratio so that they are easy to tell apart.
+fn debug_index_color(index: u32) -> [f32; 4] {
+    let hue = (index as f32 * 0.618_034).fract() * 6f32;
+    let fraction = hue.fract();
+    let (r, g, b) = match hue as u32 {
+        0 => (1f32, fraction, 0f32),
+        1 => (1f32 - fraction, 1f32, 0f32),
+        2 => (0f32, 1f32, fraction),
+        3 => (0f32, 1f32 - fraction, 1f32),
+        4 => (fraction, 0f32, 1f32),
+        _ => (1f32, 0f32, 1f32 - fraction),
+    };
+    [r, g, b, 1f32]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::ecs::schedule::ParallelSystemDescriptorCoercion;
+    use bevy::app::App;
+    use bevy::asset::{AddAsset, AssetPlugin};
+    use bevy::core::CorePlugin;
+    use bevy::ecs::schedule::{Stage, SystemStage};
+    use bevy::ecs::system::IntoSystem;
+
+    #[test]
+    fn material_id_from_u32() {
+        let material : MaterialID = 5u32.into();
+        assert_eq!(material, MaterialID::new(5));
+        assert_eq!(material.id, 5);
+    }
+
+    const STONE : MaterialID = MaterialID::new(1);
+    const GLASS : MaterialID = MaterialID::new(2);
+
+    fn registry() -> MaterialRegistry {
+        let mut registry = MaterialRegistry::new();
+        registry.register(Material { id : STONE, transparent : false, custom_model : false, tint : WHITE });
+        registry.register(Material { id : GLASS, transparent : true, custom_model : false, tint : WHITE });
+        registry
+    }
+
+    fn single_block_chunk() -> Chunk16 {
+        let mut chunk = Chunk16::empty();
+        chunk.block_at_mut(3, 4, 5).material = STONE;
+        chunk
+    }
+
+    fn positions(mesh : &Mesh) -> Vec<[f32; 3]> {
+        match mesh.attribute(Mesh::ATTRIBUTE_POSITION) {
+            Some(VertexAttributeValues::Float3(positions)) => positions.clone(),
+            _ => Vec::new(),
+        }
+    }
+
+    fn indices(mesh : &Mesh) -> Vec<u32> {
+        match mesh.indices() {
+            Some(Indices::U16(indices)) => indices.iter().map(|index| *index as u32).collect(),
+            Some(Indices::U32(indices)) => indices.clone(),
+            None => Vec::new(),
+        }
+    }
+
+    fn normals(mesh : &Mesh) -> Vec<[f32; 3]> {
+        match mesh.attribute(Mesh::ATTRIBUTE_NORMAL) {
+            Some(VertexAttributeValues::Float3(normals)) => normals.clone(),
+            _ => Vec::new(),
+        }
+    }
+
+    // Number of quads facing along `normal`
+    fn faces_towards(mesh : &Mesh, normal : [f32; 3]) -> usize {
+        normals(mesh).iter().filter(|n| **n == normal).count() / 4
+    }
+
+    fn cross(a : [f32; 3], b : [f32; 3]) -> [f32; 3] {
+        [a[1] * b[2] - a[2] * b[1], a[2] * b[0] - a[0] * b[2], a[0] * b[1] - a[1] * b[0]]
+    }
+
+    fn sub(a : [f32; 3], b : [f32; 3]) -> [f32; 3] {
+        [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+    }
+
+    #[test]
+    fn single_block_has_a_face_per_direction() {
+        let mesh = NaiveChunkMesher::generate_mesh(&single_block_chunk(), &[None; 6], &registry(), &MaterialAtlas::default())
+            .unwrap()
+            .opaque;
+        let positions = positions(&mesh);
+        let indices = indices(&mesh);
+        assert_eq!(positions.len(), 6 * 4);
+        assert_eq!(indices.len(), 6 * 6);
+
+        let mut outwards = Vec::new();
+        for face in indices.chunks(6) {
+            let [a, b, c] = [positions[face[0] as usize], positions[face[1] as usize], positions[face[2] as usize]];
+            let normal = cross(sub(b, a), sub(c, a));
+            let axis = normal.iter().position(|n| *n != 0f32).unwrap();
+            let plane = [3f32, 4f32, 5f32][axis] + if normal[axis] > 0f32 { 1f32 } else { 0f32 };
+
+            // Counter-clockwise seen from outside the block, on the side it faces
+            for triangle in face.chunks(3) {
+                let [a, b, c] = [positions[triangle[0] as usize], positions[triangle[1] as usize], positions[triangle[2] as usize]];
+                assert!([a, b, c].iter().all(|corner| corner[axis] == plane));
+                assert_eq!(cross(sub(b, a), sub(c, a)), normal);
+            }
+            outwards.push(normal);
+        }
+
+        outwards.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        assert_eq!(outwards, vec![
+            [-1f32, 0f32, 0f32],
+            [0f32, -1f32, 0f32],
+            [0f32, 0f32, -1f32],
+            [0f32, 0f32, 1f32],
+            [0f32, 1f32, 0f32],
+            [1f32, 0f32, 0f32],
+        ]);
+    }
+
+    #[test]
+    fn face_normals_point_outwards() {
+        let mesh = NaiveChunkMesher::generate_mesh(&single_block_chunk(), &[None; 6], &registry(), &MaterialAtlas::default())
+            .unwrap()
+            .opaque;
+        let positions = positions(&mesh);
+        let normals = normals(&mesh);
+
+        for (corners, face) in positions.chunks(4).zip(normals.chunks(4)) {
+            assert!(face.iter().all(|normal| *normal == face[0]));
+            assert_eq!(cross(sub(corners[1], corners[0]), sub(corners[2], corners[0])), face[0]);
+        }
+        for dir in AADirection::iter() {
+            assert_eq!(faces_towards(&mesh, dir.normal()), 1);
+        }
+        assert_eq!(AADirection::XNegative.normal(), [-1f32, 0f32, 0f32]);
+        assert_eq!(AADirection::YPositive.normal(), [0f32, 1f32, 0f32]);
+    }
+
+    fn uvs(mesh : &Mesh) -> Vec<[f32; 2]> {
+        match mesh.attribute(Mesh::ATTRIBUTE_UV_0) {
+            Some(VertexAttributeValues::Float2(uvs)) => uvs.clone(),
+            _ => Vec::new(),
+        }
+    }
+
+    #[test]
+    fn unit_faces_map_the_whole_texture() {
+        let mesh = NaiveChunkMesher::generate_mesh(&single_block_chunk(), &[None; 6], &registry(), &MaterialAtlas::default())
+            .unwrap()
+            .opaque;
+
+        // Top-left, bottom-left, bottom-right, top-right
+        for face in uvs(&mesh).chunks(4) {
+            assert_eq!(face, &[[0f32, 0f32], [0f32, 1f32], [1f32, 1f32], [1f32, 0f32]]);
+        }
+    }
+
+    fn solid_chunk() -> Chunk16 {
+        let mut chunk = Chunk16::empty();
+        for z in 0..Chunk16::SIZE_Z {
+            for y in 0..Chunk16::SIZE_Y {
+                for x in 0..Chunk16::SIZE_X {
+                    chunk.block_at_mut(x, y, z).material = STONE;
+                }
+            }
+        }
+        chunk
+    }
+
+    #[test]
+    fn solid_chunk_only_has_its_outer_faces() {
+        let mesh = NaiveChunkMesher::generate_mesh(&solid_chunk(), &[None; 6], &registry(), &MaterialAtlas::default())
+            .unwrap()
+            .opaque;
+        assert_eq!(positions(&mesh).len(), 6 * 16 * 16 * 4);
+        for dir in AADirection::iter() {
+            assert_eq!(faces_towards(&mesh, dir.normal()), 16 * 16);
+        }
+
+        // Two touching blocks hide the faces between them
+        let mut chunk = Chunk16::empty();
+        chunk.block_at_mut(0, 0, 0).material = STONE;
+        chunk.block_at_mut(1, 0, 0).material = STONE;
+        let mesh = NaiveChunkMesher::generate_mesh(&chunk, &[None; 6], &registry(), &MaterialAtlas::default())
+            .unwrap()
+            .opaque;
+        assert_eq!(positions(&mesh).len(), 10 * 4);
+        assert_eq!(faces_towards(&mesh, [1f32, 0f32, 0f32]), 1);
+    }
+
+    #[test]
+    fn neighbors_hide_the_shared_faces() {
+        let chunk = solid_chunk();
+        let neighbor = solid_chunk();
+        let mut neighbors = [None; 6];
+        neighbors[AADirection::XPositive as usize] = Some(&neighbor);
+
+        let mesh = NaiveChunkMesher::generate_mesh(&chunk, &neighbors, &registry(), &MaterialAtlas::default())
+            .unwrap()
+            .opaque;
+        assert_eq!(positions(&mesh).len(), 5 * 16 * 16 * 4);
+        assert_eq!(faces_towards(&mesh, [1f32, 0f32, 0f32]), 0);
+        assert_eq!(faces_towards(&mesh, [-1f32, 0f32, 0f32]), 16 * 16);
+    }
+
+    // An app with the resources the meshing systems need, and a place for meshes
+    fn app() -> App {
+        let mut builder = App::build();
+        builder
+            .insert_resource(registry())
+            .insert_resource(MaterialAtlas::default())
+            .add_plugin(CorePlugin)
+            .add_plugin(AssetPlugin)
+            .add_asset::<Mesh>();
+        builder.app
+    }
+
+    fn add_mesh(app : &mut App) -> Handle<Mesh> {
+        app.world.get_resource_mut::<Assets<Mesh>>().unwrap().add(Mesh::new(PrimitiveTopology::TriangleList))
+    }
+
+    fn mesh_positions(app : &App, handle : &Handle<Mesh>) -> Vec<[f32; 3]> {
+        positions(app.world.get_resource::<Assets<Mesh>>().unwrap().get(handle).unwrap())
+    }
+
+    #[test]
+    fn meshing_system_resolves_neighbors() {
+        let mut app = app();
+        let first_mesh = add_mesh(&mut app);
+        let second_mesh = add_mesh(&mut app);
+        let first = app.world.spawn().insert(solid_chunk()).insert(first_mesh.clone()).id();
+        let second = app.world.spawn().insert(solid_chunk()).insert(second_mesh.clone()).id();
+        app.world.get_mut::<Chunk16>(first).unwrap().set_neighbor(second, AADirection::XPositive);
+        app.world.get_mut::<Chunk16>(second).unwrap().set_neighbor(first, AADirection::XNegative);
+
+        let mut stage = SystemStage::single_threaded();
+        stage.add_system(chunk_meshing_system::<NaiveChunkMesher, 16, 16, 16>.system());
+        stage.run(&mut app.world);
+
+        assert_eq!(mesh_positions(&app, &first_mesh).len(), 5 * 16 * 16 * 4);
+        assert_eq!(mesh_positions(&app, &second_mesh).len(), 5 * 16 * 16 * 4);
+    }
+
+    #[test]
+    fn greedy_floor_is_one_quad_per_side() {
+        let mut chunk = Chunk16::empty();
+        for z in 0..16 {
+            for x in 0..16 {
+                chunk.block_at_mut(x, 0, z).material = STONE;
+            }
+        }
+        let mesh = GreedyChunkMesher::generate_mesh(&chunk, &[None; 6], &registry(), &MaterialAtlas::default())
+            .unwrap()
+            .opaque;
+        assert_eq!(positions(&mesh).len(), 6 * 4);
+        assert_eq!(faces_towards(&mesh, [0f32, 1f32, 0f32]), 1);
+
+        // The merged quad tiles the texture once per block
+        let normals = normals(&mesh);
+        let top_uvs : Vec<[f32; 2]> = uvs(&mesh).into_iter()
+            .zip(normals.iter())
+            .filter(|(_, normal)| **normal == [0f32, 1f32, 0f32])
+            .map(|(uv, _)| uv)
+            .collect();
+        assert!(top_uvs.contains(&[16f32, 16f32]));
+
+        // Different materials aren't merged
+        let mut chunk = Chunk16::empty();
+        chunk.block_at_mut(0, 0, 0).material = STONE;
+        chunk.block_at_mut(1, 0, 0).material = MaterialID::new(3);
+        let mut registry = registry();
+        registry.register(Material { id : MaterialID::new(3), transparent : false, custom_model : false, tint : WHITE });
+        let mesh = GreedyChunkMesher::generate_mesh(&chunk, &[None; 6], &registry, &MaterialAtlas::default())
+            .unwrap()
+            .opaque;
+        assert_eq!(positions(&mesh).len(), 10 * 4);
+    }
+
+    #[test]
+    fn greedy_covers_the_same_area_as_naive() {
+        let mut chunk = Chunk16::empty();
+        for z in 0..16 {
+            for y in 0..16 {
+                for x in 0..16 {
+                    if y <= (x / 2 + z) % 5 {
+                        chunk.block_at_mut(x, y, z).material = [STONE, GLASS][(x / 4 + z / 4) % 2];
+                    }
+                }
+            }
+        }
+
+        // Unit faces span one texture, so the far corner of a quad's UVs is its size
+        let area = |mesh : &Mesh| uvs(mesh).chunks(4).map(|face| face[2][0] * face[2][1]).sum::<f32>();
+        let registry = registry();
+        let atlas = MaterialAtlas::default();
+        let greedy = GreedyChunkMesher::generate_mesh(&chunk, &[None; 6], &registry, &atlas).unwrap();
+        let naive = NaiveChunkMesher::generate_mesh(&chunk, &[None; 6], &registry, &atlas).unwrap();
+        assert_eq!(area(&greedy.opaque), area(&naive.opaque));
+        assert_eq!(area(&greedy.transparent), area(&naive.transparent));
+        assert!(positions(&greedy.opaque).len() < positions(&naive.opaque).len());
+    }
+
+    #[test]
+    fn large_meshes_use_wide_indices() {
+        let mut chunk = Chunk::<32, 32, 32>::empty();
+        for index in 0..32 * 32 * 32 {
+            let (x, y, z) = Chunk::<32, 32, 32>::coords(index);
+            if (x + y + z) % 2 == 0 {
+                chunk.set_block(x, y, z, Block { material : STONE });
+            }
+        }
+        let mesh = NaiveChunkMesher::generate_mesh(&chunk, &[None; 6], &registry(), &MaterialAtlas::default())
+            .unwrap()
+            .opaque;
+
+        // Every block of the checkerboard keeps all of its faces
+        let vertices = 32 * 32 * 32 / 2 * 6 * 4;
+        assert!(vertices > u16::MAX as usize);
+        assert_eq!(positions(&mesh).len(), vertices);
+        match mesh.indices() {
+            Some(Indices::U32(indices)) => assert_eq!(*indices.iter().max().unwrap() as usize, vertices - 1),
+            _ => panic!("expected 32 bit indices"),
+        }
+
+        // Small meshes keep 16 bit indices
+        let mesh = NaiveChunkMesher::generate_mesh(&single_block_chunk(), &[None; 6], &registry(), &MaterialAtlas::default())
+            .unwrap()
+            .opaque;
+        assert!(matches!(mesh.indices(), Some(Indices::U16(_))));
+    }
+
+    #[test]
+    fn wide_flat_chunks() {
+        type WideChunk = Chunk<32, 8, 32>;
+
+        let mut chunk = WideChunk::empty();
+        for z in 0..32 {
+            for y in 0..8 {
+                for x in 0..32 {
+                    chunk.block_at_mut(x, y, z).material = STONE;
+                }
+            }
+        }
+        assert_eq!(WideChunk::size(), vector![32, 8, 32]);
+        let registry = registry();
+        let atlas = MaterialAtlas::default();
+
+        let naive = NaiveChunkMesher::generate_mesh(&chunk, &[None; 6], &registry, &atlas).unwrap().opaque;
+        assert_eq!(faces_towards(&naive, [0f32, 1f32, 0f32]), 32 * 32);
+        assert_eq!(faces_towards(&naive, [1f32, 0f32, 0f32]), 32 * 8);
+        assert_eq!(positions(&naive).len(), (2 * 32 * 32 + 4 * 32 * 8) * 4);
+
+        let greedy = GreedyChunkMesher::generate_mesh(&chunk, &[None; 6], &registry, &atlas).unwrap().opaque;
+        assert_eq!(positions(&greedy).len(), 6 * 4);
+        let max = positions(&greedy).iter().fold([0f32; 3], |max, p| [max[0].max(p[0]), max[1].max(p[1]), max[2].max(p[2])]);
+        assert_eq!(max, [32f32, 8f32, 32f32]);
+    }
+
+    #[test]
+    fn try_block_at_checks_bounds() {
+        let mut chunk = Chunk::<4, 5, 6>::empty();
+        assert!(chunk.try_block_at(0, 0, 0).is_some());
+        assert!(chunk.try_block_at(3, 4, 5).is_some());
+        assert!(chunk.try_block_at(4, 0, 0).is_none());
+        assert!(chunk.try_block_at(0, 5, 0).is_none());
+        assert!(chunk.try_block_at(0, 0, 6).is_none());
+        assert!(chunk.try_block_at(usize::MAX, 0, 0).is_none());
+
+        chunk.try_block_at_mut(3, 0, 0).unwrap().material = STONE;
+        assert!(chunk.try_block_at_mut(0, 0, 99).is_none());
+        assert_eq!(chunk.block_at(3, 0, 0).material, STONE);
+    }
+
+    #[test]
+    #[should_panic]
+    fn block_at_panics_out_of_bounds() {
+        Chunk::<4, 5, 6>::empty().block_at(0, 5, 0);
+    }
+
+    #[test]
+    fn index_and_coords_round_trip() {
+        type OddChunk = Chunk<32, 4, 8>;
+
+        for z in 0..8 {
+            for y in 0..4 {
+                for x in 0..32 {
+                    assert_eq!(OddChunk::coords(OddChunk::index(x, y, z)), (x, y, z));
+                }
+            }
+        }
+        for index in 0..32 * 4 * 8 {
+            let (x, y, z) = OddChunk::coords(index);
+            assert_eq!(OddChunk::index(x, y, z), index);
+        }
+        assert_eq!(OddChunk::index(1, 0, 0), 1);
+        assert_eq!(OddChunk::index(31, 3, 7), 32 * 4 * 8 - 1);
+    }
+
+    #[test]
+    fn only_edge_changes_reach_neighbors() {
+        let mut world = World::new();
+        let chunk = world.spawn().insert(Chunk16::empty()).id();
+        let negative = world.spawn().insert(Chunk16::empty()).id();
+        let positive = world.spawn().insert(Chunk16::empty()).id();
+        for entity in [chunk, negative, positive].iter() {
+            world.get_mut::<Chunk16>(*entity).unwrap().clear_change();
+        }
+        {
+            let mut chunk = world.get_mut::<Chunk16>(chunk).unwrap();
+            chunk.set_neighbor(negative, AADirection::XNegative);
+            chunk.set_neighbor(positive, AADirection::XPositive);
+            chunk.block_at_mut(0, 5, 5).material = STONE;
+            chunk.mark_block_changed(0, 5, 5);
+        }
+
+        let mut stage = SystemStage::single_threaded();
+        stage.add_system(chunk_neighbor_change_system::<16, 16, 16>.system());
+        stage.run(&mut world);
+
+        assert!(world.get::<Chunk16>(chunk).unwrap().has_changed());
+        assert!(world.get::<Chunk16>(negative).unwrap().has_changed());
+        assert!(!world.get::<Chunk16>(positive).unwrap().has_changed());
+        assert!(!world.get::<Chunk16>(chunk).unwrap().has_changed_edge(AADirection::XNegative));
+
+        // Interior changes stay inside the chunk
+        world.get_mut::<Chunk16>(negative).unwrap().clear_change();
+        world.get_mut::<Chunk16>(chunk).unwrap().mark_block_changed(5, 5, 5);
+        stage.run(&mut world);
+        assert!(!world.get::<Chunk16>(negative).unwrap().has_changed());
+    }
+
+    #[test]
+    fn set_block_tracks_changes() {
+        let mut chunk = Chunk16::empty();
+        chunk.clear_change();
+
+        let previous = chunk.set_block(1, 1, 1, Block::default());
+        assert_eq!(previous.material, AIR.id);
+        assert!(!chunk.has_changed());
+
+        let previous = chunk.set_block(1, 1, 1, Block { material : STONE });
+        assert_eq!(previous.material, AIR.id);
+        assert!(chunk.has_changed());
+        assert_eq!(chunk.block_at(1, 1, 1).material, STONE);
+        assert_eq!(chunk.set_block(1, 1, 1, Block::default()).material, STONE);
+    }
+
+    #[test]
+    fn uvs_stay_in_the_atlas_rect() {
+        let dirt = MaterialID::new(3);
+        let mut atlas = MaterialAtlas::new();
+        atlas.insert(STONE, UvRect::new([0f32, 0f32], [0.5f32, 0.5f32]));
+        atlas.insert(dirt, UvRect::new([0.5f32, 0.5f32], [1f32, 1f32]));
+
+        let mut chunk = Chunk16::empty();
+        chunk.set_block(0, 0, 0, Block { material : STONE });
+        chunk.set_block(5, 0, 0, Block { material : dirt });
+        let mut registry = registry();
+        registry.register(Material { id : dirt, transparent : false, custom_model : false, tint : WHITE });
+        let mesh = NaiveChunkMesher::generate_mesh(&chunk, &[None; 6], &registry, &atlas).unwrap().opaque;
+
+        for (uv, position) in uvs(&mesh).iter().zip(positions(&mesh).iter()) {
+            if position[0] <= 1f32 {
+                assert!(uv[0] <= 0.5f32 && uv[1] <= 0.5f32, "{:?}", uv);
+            } else {
+                assert!(uv[0] >= 0.5f32 && uv[1] >= 0.5f32, "{:?}", uv);
+            }
+        }
+        assert_eq!(atlas.rect(MaterialID::new(9), AADirection::XPositive), UvRect::FULL);
+    }
+
+    #[test]
+    fn grass_has_its_own_top_texture() {
+        let grass = MaterialID::new(3);
+        let side = UvRect::new([0f32, 0f32], [0.25f32, 0.25f32]);
+        let top = UvRect::new([0.5f32, 0.5f32], [0.75f32, 0.75f32]);
+        let mut atlas = MaterialAtlas::new();
+        atlas.insert(grass, side);
+        atlas.insert_face(grass, AADirection::YPositive, top);
+
+        assert_eq!(atlas.rect(grass, AADirection::XPositive), side);
+        assert_eq!(atlas.rect(grass, AADirection::YPositive), top);
+        assert_eq!(atlas.rect(grass, AADirection::YNegative), side);
+        assert_eq!(atlas.rect(MaterialID::new(9), AADirection::YPositive), UvRect::FULL);
+
+        let mut chunk = Chunk16::empty();
+        chunk.set_block(0, 0, 0, Block { material : grass });
+        let mut registry = registry();
+        registry.register(Material { id : grass, transparent : false, custom_model : false, tint : WHITE });
+        let mesh = GreedyChunkMesher::generate_mesh(&chunk, &[None; 6], &registry, &atlas).unwrap().opaque;
+        for (uv, normal) in uvs(&mesh).iter().zip(normals(&mesh).iter()) {
+            if *normal == [0f32, 1f32, 0f32] {
+                assert!(uv[0] >= 0.5f32 && uv[1] >= 0.5f32);
+            } else {
+                assert!(uv[0] <= 0.25f32 && uv[1] <= 0.25f32);
+            }
+        }
+    }
+
+    #[test]
+    fn glass_next_to_stone_keeps_both_faces() {
+        let mut chunk = Chunk16::empty();
+        chunk.set_block(0, 0, 0, Block { material : GLASS });
+        chunk.set_block(1, 0, 0, Block { material : STONE });
+        chunk.set_block(0, 1, 0, Block { material : GLASS });
+        let registry = registry();
+        let atlas = MaterialAtlas::default();
+
+        let naive = NaiveChunkMesher::generate_mesh(&chunk, &[None; 6], &registry, &atlas).unwrap();
+        let greedy = GreedyChunkMesher::generate_mesh(&chunk, &[None; 6], &registry, &atlas).unwrap();
+        for meshes in [&naive, &greedy].iter() {
+            // The stone keeps its face towards the glass
+            assert_eq!(positions(&meshes.opaque).len(), 6 * 4);
+            assert_eq!(faces_towards(&meshes.opaque, [-1f32, 0f32, 0f32]), 1);
+
+            // The glass doesn't draw a face against the stone, but does on its side above it
+            let transparent_positions = positions(&meshes.transparent);
+            let transparent_normals = normals(&meshes.transparent);
+            assert!(!transparent_positions.iter()
+                .zip(transparent_normals.iter())
+                .any(|(position, normal)| *normal == [1f32, 0f32, 0f32] && position[1] < 1f32));
+            assert!(faces_towards(&meshes.transparent, [1f32, 0f32, 0f32]) > 0);
+        }
+
+        // Two glass blocks share no face, and one is hidden by the stone
+        assert_eq!(positions(&naive.transparent).len(), (2 * 6 - 2 - 1) * 4);
+    }
+
+    #[test]
+    fn custom_models_are_collected_instead_of_meshed() {
+        let torch = MaterialID::new(3);
+        let mut registry = registry();
+        registry.register(Material { id : torch, transparent : false, custom_model : true, tint : WHITE });
+
+        let mut chunk = Chunk16::empty();
+        chunk.set_block(2, 3, 4, Block { material : torch });
+        chunk.set_block(3, 3, 4, Block { material : STONE });
+        let atlas = MaterialAtlas::default();
+
+        let naive = NaiveChunkMesher::generate_mesh(&chunk, &[None; 6], &registry, &atlas).unwrap();
+        let greedy = GreedyChunkMesher::generate_mesh(&chunk, &[None; 6], &registry, &atlas).unwrap();
+        for meshes in [naive, greedy].iter() {
+            // The stone next to the torch keeps all of its faces
+            assert_eq!(positions(&meshes.opaque).len(), 6 * 4);
+            assert!(positions(&meshes.transparent).is_empty());
+            assert_eq!(meshes.custom_models, vec![(point![2, 3, 4], torch)]);
+        }
+    }
+
+    #[test]
+    fn registry_lookups() {
+        let stone = Material { id : STONE, transparent : false, custom_model : false, tint : WHITE };
+        let mut registry = MaterialRegistry::new();
+        assert_eq!(registry.get(AIR.id), Some(&AIR));
+        assert!(registry.get(STONE).is_none());
+        assert!(!registry.contains(STONE));
+
+        registry.register(stone.clone());
+        assert!(registry.contains(STONE));
+        assert_eq!(registry.get(STONE), Some(&stone));
+        assert_eq!(registry.iter().count(), 2);
+        assert_eq!(MaterialRegistry::default().get(AIR.id), Some(&AIR));
+    }
+
+    fn colors(mesh : &Mesh) -> Vec<[f32; 4]> {
+        match mesh.attribute(Mesh::ATTRIBUTE_COLOR) {
+            Some(VertexAttributeValues::Float4(colors)) => colors.clone(),
+            _ => Vec::new(),
+        }
+    }
+
+    #[test]
+    fn occluded_corners_are_darker() {
+        // A block on the floor with another one above and next to it
+        let mut chunk = Chunk16::empty();
+        chunk.set_block(0, 0, 0, Block { material : STONE });
+        chunk.set_block(1, 1, 0, Block { material : STONE });
+        let mesh = NaiveChunkMesher::generate_mesh(&chunk, &[None; 6], &registry(), &MaterialAtlas::default())
+            .unwrap()
+            .opaque;
+
+        let positions = positions(&mesh);
+        let normals = normals(&mesh);
+        let colors = colors(&mesh);
+        let top_of_first : Vec<usize> = (0..positions.len())
+            .filter(|i| normals[*i] == [0f32, 1f32, 0f32] && positions[*i][1] == 1f32 && positions[*i][0] <= 1f32)
+            .collect();
+        assert_eq!(top_of_first.len(), 4);
+        for i in top_of_first {
+            if positions[i][0] == 1f32 {
+                assert!(colors[i][0] < 1f32, "{:?}", positions[i]);
+            } else {
+                assert_eq!(colors[i][0], 1f32, "{:?}", positions[i]);
+            }
+        }
+
+        // The top of the upper block is open
+        assert!((0..positions.len())
+            .filter(|i| normals[*i] == [0f32, 1f32, 0f32] && positions[*i][1] == 2f32)
+            .all(|i| colors[i] == WHITE));
+    }
+
+    #[test]
+    fn tinted_materials_color_their_faces() {
+        let red = MaterialID::new(3);
+        let mut registry = registry();
+        registry.register(Material { id : red, transparent : false, custom_model : false, tint : [1f32, 0f32, 0f32, 1f32] });
+
+        let mut chunk = Chunk16::empty();
+        chunk.set_block(3, 3, 3, Block { material : red });
+        chunk.set_block(8, 8, 8, Block { material : STONE });
+        let mesh = NaiveChunkMesher::generate_mesh(&chunk, &[None; 6], &registry, &MaterialAtlas::default())
+            .unwrap()
+            .opaque;
+
+        for (position, color) in positions(&mesh).iter().zip(colors(&mesh).iter()) {
+            if position[0] < 5f32 {
+                assert_eq!(*color, [1f32, 0f32, 0f32, 1f32]);
+            } else {
+                assert_eq!(*color, WHITE);
+            }
+        }
+    }
+
+    #[test]
+    fn iter_blocks_visits_cells_in_index_order() {
+        type SmallChunk = Chunk<3, 4, 5>;
+
+        let mut chunk = SmallChunk::empty();
+        assert_eq!(chunk.iter_blocks().count(), 3 * 4 * 5);
+        for (index, (x, y, z, _)) in chunk.iter_blocks().enumerate() {
+            assert_eq!(SmallChunk::index(x, y, z), index);
+        }
+
+        for (x, _, _, block) in chunk.iter_blocks_mut() {
+            if x == 2 {
+                block.material = STONE;
+            }
+        }
+        assert_eq!(chunk.block_at(2, 3, 4).material, STONE);
+        assert_eq!(chunk.block_at(1, 3, 4).material, AIR.id);
+        assert_eq!(chunk.iter_blocks().filter(|(_, _, _, block)| block.material == STONE).count(), 4 * 5);
+    }
+
+    #[test]
+    fn serialization_round_trips() {
+        // One run of air
+        let data = Chunk16::empty().serialize();
+        assert_eq!(data.len(), 8);
+        let chunk = Chunk16::deserialize(&data).unwrap();
+        assert!(chunk.iter_blocks().all(|(_, _, _, block)| block.material == AIR.id));
+
+        let chunk = Chunk16::deserialize(&solid_chunk().serialize()).unwrap();
+        assert!(chunk.iter_blocks().all(|(_, _, _, block)| block.material == STONE));
+
+        let mut checkerboard = Chunk16::empty();
+        for index in 0..16 * 16 * 16 {
+            let (x, y, z) = Chunk16::coords(index);
+            if (x + y + z) % 2 == 0 {
+                checkerboard.set_block(x, y, z, Block { material : STONE });
+            }
+        }
+        let data = checkerboard.serialize();
+        assert!(data.len() > 16 * 16 * 16 / 2 * 8 && data.len() < 16 * 16 * 16 * 8);
+        let chunk = Chunk16::deserialize(&data).unwrap();
+        assert!(chunk.iter_blocks()
+            .zip(checkerboard.iter_blocks())
+            .all(|((_, _, _, decoded), (_, _, _, block))| decoded.material == block.material));
+    }
+
+    #[test]
+    fn deserialize_validates_the_length() {
+        let data = solid_chunk().serialize();
+        assert_eq!(Chunk16::deserialize(&data[..7]).err(), Some(ChunkDecodeError::TruncatedRun));
+        assert!(Chunk::<8, 8, 8>::deserialize(&data).is_err());
+        assert!(matches!(Chunk::<32, 32, 32>::deserialize(&data), Err(ChunkDecodeError::TooFewBlocks { .. })));
+
+        let mut too_long = data.clone();
+        too_long.extend_from_slice(&data);
+        assert_eq!(Chunk16::deserialize(&too_long).err(), Some(ChunkDecodeError::TooManyBlocks));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn blocks_serialize_to_json() {
+        let block = Block { material : STONE };
+        let json = serde_json::to_string(&block).unwrap();
+        assert_eq!(serde_json::from_str::<Block>(&json).unwrap().material, block.material);
+
+        let json = serde_json::to_string(&AADirection::ZNegative).unwrap();
+        assert_eq!(serde_json::from_str::<AADirection>(&json).unwrap(), AADirection::ZNegative);
+    }
+
+    // Meshes a row of linked chunks with differing terrain in one run of `stage`, and
+    // returns the vertex positions of their meshes
+    fn mesh_row(mut stage : SystemStage) -> Vec<Vec<[f32; 3]>> {
+        let mut app = app();
+        let mut handles = Vec::new();
+        let mut entities = Vec::new();
+        for i in 0..6 {
+            let mut chunk = Chunk16::empty();
+            for index in 0..16 * 16 * 16 {
+                let (x, y, z) = Chunk16::coords(index);
+                if y <= i + (x * z) % 5 {
+                    chunk.set_block(x, y, z, Block { material : STONE });
+                }
+            }
+            let handle = add_mesh(&mut app);
+            entities.push(app.world.spawn().insert(chunk).insert(handle.clone()).id());
+            handles.push(handle);
+        }
+        for pair in entities.windows(2) {
+            assert!(link_chunks::<16, 16, 16>(&mut app.world, pair[0], pair[1], AADirection::XPositive));
+        }
+
+        stage.run(&mut app.world);
+        handles.iter().map(|handle| mesh_positions(&app, handle)).collect()
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn parallel_meshing_matches_sequential() {
+        let mut sequential = SystemStage::single_threaded();
+        sequential.add_system(chunk_meshing_system::<GreedyChunkMesher, 16, 16, 16>.system());
+        let mut parallel = SystemStage::single_threaded();
+        parallel.add_system(parallel_chunk_meshing_system::<GreedyChunkMesher, 16, 16, 16>.system());
+
+        let sequential = mesh_row(sequential);
+        assert!(sequential.iter().all(|positions| !positions.is_empty()));
+        assert_eq!(mesh_row(parallel), sequential);
+    }
+
+    #[test]
+    fn collision_mesh_merges_solid_slabs() {
+        // A two block high slab of alternating materials, with a lone glass block above
+        let mut chunk = Chunk16::empty();
+        let dirt = MaterialID::new(3);
+        for index in 0..16 * 16 * 16 {
+            let (x, y, z) = Chunk16::coords(index);
+            if y < 2 {
+                chunk.set_block(x, y, z, Block { material : [STONE, dirt][x % 2] });
+            }
+        }
+        chunk.set_block(5, 5, 5, Block { material : GLASS });
+        let mut registry = registry();
+        registry.register(Material { id : dirt, transparent : false, custom_model : false, tint : WHITE });
+
+        let mesh = generate_collision_mesh(&chunk, &[None; 6], &registry);
+        assert_eq!(positions(&mesh).len(), 6 * 4);
+        assert_eq!(indices(&mesh).len(), 6 * 6);
+        assert!(mesh.attribute(Mesh::ATTRIBUTE_NORMAL).is_none());
+        assert!(mesh.attribute(Mesh::ATTRIBUTE_UV_0).is_none());
+        assert!(mesh.attribute(Mesh::ATTRIBUTE_COLOR).is_none());
+    }
+
+    // Largest coordinate along each axis
+    fn max_position(mesh : &Mesh) -> [f32; 3] {
+        positions(mesh).iter().fold([0f32; 3], |max, p| [max[0].max(p[0]), max[1].max(p[1]), max[2].max(p[2])])
+    }
+
+    #[test]
+    fn lod_zero_is_full_resolution() {
+        let mut chunk = Chunk16::empty();
+        for z in 0..16 {
+            for y in 0..4 {
+                for x in 0..16 {
+                    chunk.set_block(x, y, z, Block { material : STONE });
+                }
+            }
+        }
+        chunk.set_block(4, 4, 4, Block { material : GLASS });
+        let registry = registry();
+        let atlas = MaterialAtlas::default();
+
+        let naive = NaiveChunkMesher::generate_mesh(&chunk, &[None; 6], &registry, &atlas).unwrap();
+        let naive_lod = NaiveChunkMesher::generate_mesh_lod(&chunk, &[None; 6], &registry, &atlas, 0).unwrap();
+        assert_eq!(positions(&naive_lod.opaque), positions(&naive.opaque));
+        assert_eq!(positions(&naive_lod.transparent), positions(&naive.transparent));
+
+        let greedy = GreedyChunkMesher::generate_mesh(&chunk, &[None; 6], &registry, &atlas).unwrap();
+        let greedy_lod = GreedyChunkMesher::generate_mesh_lod(&chunk, &[None; 6], &registry, &atlas, 0).unwrap();
+        assert_eq!(positions(&greedy_lod.opaque), positions(&greedy.opaque));
+    }
+
+    #[test]
+    fn lod_one_has_a_quarter_of_the_faces() {
+        let chunk = solid_chunk();
+        let registry = registry();
+        let atlas = MaterialAtlas::default();
+
+        let mesh = NaiveChunkMesher::generate_mesh_lod(&chunk, &[None; 6], &registry, &atlas, 1).unwrap().opaque;
+        for dir in AADirection::iter() {
+            assert_eq!(faces_towards(&mesh, dir.normal()), 16 * 16 / 4);
+        }
+        assert_eq!(max_position(&mesh), [16f32; 3]);
+        assert!(positions(&mesh).iter().all(|p| p.iter().all(|coord| coord % 2f32 == 0f32)));
+
+        let mesh = NaiveChunkMesher::generate_mesh_lod(&chunk, &[None; 6], &registry, &atlas, 4).unwrap().opaque;
+        assert_eq!(positions(&mesh).len(), 6 * 4);
+        assert_eq!(max_position(&mesh), [16f32; 3]);
+    }
+
+    #[test]
+    fn lod_meshes_greedily_with_the_config() {
+        let chunk = solid_chunk();
+        let registry = registry();
+        let atlas = MaterialAtlas::default();
+
+        let mesh = GreedyChunkMesher::generate_mesh_lod(&chunk, &[None; 6], &registry, &atlas, 1).unwrap().opaque;
+        assert_eq!(positions(&mesh).len(), 6 * 4);
+        assert_eq!(max_position(&mesh), [16f32; 3]);
+
+        // Two cells are four blocks
+        let config = MeshConfig { lod : 1, max_quad_size : 2, ..MeshConfig::default() };
+        let mesh = generate_mesh_with(&chunk, &[None; 6], &registry, &atlas, &config).unwrap().opaque;
+        assert_eq!(faces_towards(&mesh, [0f32, 1f32, 0f32]), 4 * 4);
+        assert_eq!(positions(&mesh).len(), 6 * 4 * 4 * 4);
+
+        // A floor where merging along X first leaves three quads on top, and merging
+        // along Z first two. Each block is doubled along every axis, so the coarse grid
+        // has the same shape.
+        let mut chunk = Chunk16::empty();
+        for (x, z) in [(1, 0), (0, 1), (1, 1), (2, 1)].iter() {
+            chunk.fill_region(point![x * 2, 0, z * 2], point![x * 2 + 1, 1, z * 2 + 1], STONE);
+        }
+        let top_quads = |sweep_order : [Axis; 3]| {
+            let config = MeshConfig { lod : 1, sweep_order, ..MeshConfig::default() };
+            let mesh = generate_mesh_with(&chunk, &[None; 6], &registry, &atlas, &config).unwrap().opaque;
+            faces_towards(&mesh, [0f32, 1f32, 0f32])
+        };
+        assert_eq!(top_quads([Axis::X, Axis::Z, Axis::Y]), 3);
+        assert_eq!(top_quads([Axis::Z, Axis::X, Axis::Y]), 2);
+    }
+
+    #[test]
+    fn lod_cells_are_cut_off_at_the_chunk_size() {
+        let mut chunk = Chunk::<5, 3, 5>::empty();
+        for z in 0..5 {
+            for y in 0..3 {
+                for x in 0..5 {
+                    chunk.set_block(x, y, z, Block { material : STONE });
+                }
+            }
+        }
+        let mesh = NaiveChunkMesher::generate_mesh_lod(&chunk, &[None; 6], &registry(), &MaterialAtlas::default(), 1)
+            .unwrap()
+            .opaque;
+        assert_eq!(max_position(&mesh), [5f32, 3f32, 5f32]);
+        assert_eq!(faces_towards(&mesh, [0f32, 1f32, 0f32]), 3 * 3);
+        assert_eq!(faces_towards(&mesh, [1f32, 0f32, 0f32]), 3 * 2);
+    }
+
+    #[test]
+    fn lod_culls_against_neighbors() {
+        let chunk = solid_chunk();
+        let neighbor = solid_chunk();
+        let mut neighbors = [None; 6];
+        neighbors[AADirection::XPositive as usize] = Some(&neighbor);
+
+        let mesh = GreedyChunkMesher::generate_mesh_lod(&chunk, &neighbors, &registry(), &MaterialAtlas::default(), 2)
+            .unwrap()
+            .opaque;
+        assert_eq!(faces_towards(&mesh, [1f32, 0f32, 0f32]), 0);
+        assert_eq!(positions(&mesh).len(), 5 * 4);
+    }
+
+    #[test]
+    fn missing_mesh_assets_are_skipped() {
+        let mut app = app();
+        let removed = add_mesh(&mut app);
+        let stale = removed.clone_weak();
+        app.world.get_resource_mut::<Assets<Mesh>>().unwrap().remove(&removed);
+        let available = add_mesh(&mut app);
+        let waiting = app.world.spawn().insert(solid_chunk()).insert(stale).id();
+        let meshed = app.world.spawn().insert(solid_chunk()).insert(available.clone()).id();
+
+        let mut stage = SystemStage::single_threaded();
+        stage.add_system(chunk_meshing_system::<NaiveChunkMesher, 16, 16, 16>.system().label("meshing"));
+        stage.add_system(chunk_end_of_tick_system::<16, 16, 16>.system().after("meshing"));
+        stage.run(&mut app.world);
+
+        // The chunk without a mesh stays dirty to be meshed once it has one
+        assert!(app.world.get::<Chunk16>(waiting).unwrap().has_changed());
+        assert!(!app.world.get::<Chunk16>(meshed).unwrap().has_changed());
+        assert_eq!(mesh_positions(&app, &available).len(), 6 * 16 * 16 * 4);
+
+        let handle = app.world.get::<Handle<Mesh>>(waiting).unwrap().clone_weak();
+        app.world.get_resource_mut::<Assets<Mesh>>().unwrap().set_untracked(&handle, Mesh::new(PrimitiveTopology::TriangleList));
+        stage.run(&mut app.world);
+        assert!(!app.world.get::<Chunk16>(waiting).unwrap().has_changed());
+        assert_eq!(mesh_positions(&app, &handle).len(), 6 * 16 * 16 * 4);
+    }
+
+    #[test]
+    fn config_flags_toggle_their_features() {
+        let mesh_with = |chunk : &Chunk16, config : MeshConfig| {
+            generate_mesh_with(chunk, &[None; 6], &registry(), &MaterialAtlas::default(), &config)
+                .unwrap()
+                .opaque
+        };
+        assert_eq!(MeshConfig::default(), MeshConfig::DEFAULT);
+
+        let chunk = solid_chunk();
+        let greedy = GreedyChunkMesher::generate_mesh(&chunk, &[None; 6], &registry(), &MaterialAtlas::default())
+            .unwrap()
+            .opaque;
+        assert_eq!(positions(&mesh_with(&chunk, MeshConfig::DEFAULT)), positions(&greedy));
+        assert_eq!(positions(&mesh_with(&chunk, MeshConfig { greedy : false, ..MeshConfig::DEFAULT })).len(), 6 * 16 * 16 * 4);
+
+        // Without culling every block keeps all of its faces
+        let unculled = mesh_with(&chunk, MeshConfig { greedy : false, cull_interior : false, ..MeshConfig::DEFAULT });
+        assert_eq!(positions(&unculled).len(), 6 * 16 * 16 * 16 * 4);
+
+        let mut chunk = Chunk16::empty();
+        chunk.set_block(0, 0, 0, Block { material : STONE });
+        chunk.set_block(1, 1, 0, Block { material : STONE });
+        let occluded = mesh_with(&chunk, MeshConfig::DEFAULT);
+        let flat = mesh_with(&chunk, MeshConfig { ambient_occlusion : false, ..MeshConfig::DEFAULT });
+        assert!(colors(&occluded).iter().any(|color| color[0] < 1f32));
+        assert!(colors(&flat).iter().all(|color| color[0] == 1f32));
+    }
+
+    #[test]
+    fn end_of_tick_only_touches_dirty_chunks() {
+        let mut app = app();
+        let dirty = app.world.spawn().insert(Chunk16::empty()).id();
+        let clean = app.world.spawn().insert(Chunk16::empty()).id();
+        app.world.get_mut::<Chunk16>(clean).unwrap().clear_change();
+
+        let mut stage = SystemStage::single_threaded();
+        stage.add_system(chunk_end_of_tick_system::<16, 16, 16>.system().label("tick"));
+        stage.add_system((|q : Query<Entity, Changed<Chunk16>>, mut seen : ResMut<Vec<Entity>>| {
+            *seen = q.iter().collect();
+        }).system().after("tick"));
+        app.world.insert_resource(Vec::<Entity>::new());
+
+        stage.run(&mut app.world);
+        assert!(!app.world.get::<Chunk16>(dirty).unwrap().has_changed());
+        assert!(!app.world.get::<Chunk16>(clean).unwrap().has_changed());
+
+        // With every chunk clean, the next tick doesn't mutate any of them
+        stage.run(&mut app.world);
+        assert!(app.world.get_resource::<Vec<Entity>>().unwrap().is_empty());
+    }
+
+    #[test]
+    fn opposite_directions_and_offsets() {
+        for direction in AADirection::iter() {
+            assert_eq!(direction.opposite().opposite(), direction);
+            assert_ne!(direction.opposite(), direction);
+
+            let (x, y, z) = direction.offset();
+            assert_eq!([x as f32, y as f32, z as f32], direction.normal());
+            assert_eq!(x.abs() + y.abs() + z.abs(), 1);
+            assert_eq!(x + y + z > 0, direction.is_positive());
+
+            let (opposite_x, opposite_y, opposite_z) = direction.opposite().offset();
+            assert_eq!((x + opposite_x, y + opposite_y, z + opposite_z), (0, 0, 0));
+        }
+    }
+
+    #[test]
+    fn directions_iterate_in_discriminant_order() {
+        let directions : Vec<AADirection> = AADirection::iter().collect();
+        assert_eq!(directions.len(), 6);
+        for (i, direction) in directions.iter().enumerate() {
+            assert_eq!(*direction as usize, i);
+        }
+        assert_eq!(directions, AADirection::ALL.to_vec());
+    }
+
+    #[test]
+    fn link_chunks_sets_both_neighbors() {
+        let mut app = app();
+        let a = app.world.spawn().insert(Chunk16::empty()).id();
+        let b = app.world.spawn().insert(Chunk16::empty()).id();
+        let not_a_chunk = app.world.spawn().id();
+
+        assert!(link_chunks::<16, 16, 16>(&mut app.world, a, b, AADirection::YPositive));
+        assert_eq!(app.world.get::<Chunk16>(a).unwrap().get_neighbor(AADirection::YPositive), Some(b));
+        assert_eq!(app.world.get::<Chunk16>(b).unwrap().get_neighbor(AADirection::YNegative), Some(a));
+
+        assert!(!link_chunks::<16, 16, 16>(&mut app.world, a, not_a_chunk, AADirection::XPositive));
+        assert_eq!(app.world.get::<Chunk16>(a).unwrap().get_neighbor(AADirection::XPositive), None);
+    }
+
+    #[test]
+    fn empty_chunks() {
+        let mut chunk = Chunk16::empty();
+        assert!(chunk.is_empty());
+        chunk.set_block(3, 4, 5, Block { material : STONE });
+        assert!(!chunk.is_empty());
+        chunk.set_block(3, 4, 5, Block::default());
+        assert!(chunk.is_empty());
+        assert!(!single_block_chunk().is_empty());
+    }
+
+    #[test]
+    fn solid_count_follows_block_changes() {
+        let mut chunk = Chunk::<2, 2, 2>::empty();
+        assert_eq!(chunk.solid_count(), 0);
+        assert!(!chunk.is_full());
+
+        for (x, y, z, _) in Chunk::<2, 2, 2>::empty().iter_blocks() {
+            chunk.set_block(x, y, z, Block { material : STONE });
+        }
+        assert_eq!(chunk.solid_count(), 8);
+        assert!(chunk.is_full());
+
+        // Replacing one solid block with another doesn't change the count, nor does
+        // clearing a block twice
+        chunk.set_block(0, 0, 0, Block { material : GLASS });
+        assert_eq!(chunk.solid_count(), 8);
+        chunk.set_block(0, 0, 0, Block::default());
+        chunk.set_block(0, 0, 0, Block::default());
+        assert_eq!(chunk.solid_count(), 7);
+        assert!(!chunk.is_full());
+
+        // Changes through the raw accessor are counted as well
+        chunk.block_at_mut(1, 1, 1).material = AIR.id;
+        assert_eq!(chunk.solid_count(), 6);
+        chunk.recount_solid();
+        chunk.set_block(1, 1, 1, Block { material : STONE });
+        assert_eq!(chunk.solid_count(), 7);
+    }
+
+    #[test]
+    fn stats_count_emitted_and_culled_faces() {
+        let interior_faces = 6 * 16 * 16 * 16 - 6 * 16 * 16;
+
+        let naive = NaiveChunkMesher::generate_mesh(&solid_chunk(), &[None; 6], &registry(), &MaterialAtlas::default())
+            .unwrap()
+            .stats;
+        assert_eq!(naive.faces_emitted, 6 * 16 * 16);
+        assert_eq!(naive.faces_culled, interior_faces);
+        assert_eq!(naive.vertices, 6 * 16 * 16 * 4);
+        assert_eq!(naive.indices, 6 * 16 * 16 * 6);
+
+        let greedy = GreedyChunkMesher::generate_mesh(&solid_chunk(), &[None; 6], &registry(), &MaterialAtlas::default())
+            .unwrap()
+            .stats;
+        assert_eq!(greedy.faces_emitted, 6);
+        assert_eq!(greedy.faces_culled, interior_faces);
+    }
+
+    #[test]
+    fn buffers_are_reused_across_chunks() {
+        let naive = |chunk : &Chunk16| {
+            NaiveChunkMesher::generate_mesh(chunk, &[None; 6], &registry(), &MaterialAtlas::default()).unwrap()
+        };
+        let mut buffers = ChunkMeshBuffers::new();
+
+        let solid = NaiveChunkMesher::generate_mesh_with_buffers(&solid_chunk(), &[None; 6], &registry(), &MaterialAtlas::default(), &mut buffers).unwrap();
+        assert_eq!(positions(&solid.opaque), positions(&naive(&solid_chunk()).opaque));
+
+        // The vertex data was moved into the mesh, handing the mesh back returns its buffers
+        assert_eq!(buffers.opaque.vertex_capacity(), 0);
+        buffers.reclaim(Some(solid.opaque), Some(solid.transparent));
+        let capacity = buffers.opaque.vertex_capacity();
+        assert!(capacity >= 6 * 16 * 16 * 4);
+        assert_eq!(buffers.opaque.vertex_count(), 0);
+
+        let single = NaiveChunkMesher::generate_mesh_with_buffers(&single_block_chunk(), &[None; 6], &registry(), &MaterialAtlas::default(), &mut buffers).unwrap();
+        assert_eq!(positions(&single.opaque), positions(&naive(&single_block_chunk()).opaque));
+        assert_eq!(indices(&single.opaque), indices(&naive(&single_block_chunk()).opaque));
+        buffers.reclaim(Some(single.opaque), None);
+        assert_eq!(buffers.opaque.vertex_capacity(), capacity);
+
+        // Clearing keeps the allocations
+        buffers.opaque.positions.extend_from_slice(&[[0f32; 3]; 8]);
+        buffers.clear();
+        assert_eq!(buffers.opaque.vertex_count(), 0);
+        assert_eq!(buffers.opaque.vertex_capacity(), capacity);
+    }
+
+    #[test]
+    fn smooth_normals_average_at_corners() {
+        let mut chunk = Chunk16::empty();
+        for z in 0..3 {
+            for x in 0..3 {
+                chunk.set_block(x, 0, z, Block { material : STONE });
+            }
+        }
+        let config = MeshConfig { greedy : false, smooth_normals : true, ..MeshConfig::DEFAULT };
+        let mesh = generate_mesh_with(&chunk, &[None; 6], &registry(), &MaterialAtlas::default(), &config)
+            .unwrap()
+            .opaque;
+
+        let vertices = positions(&mesh);
+        let smoothed = normals(&mesh);
+        let corner = 1f32 / 3f32.sqrt();
+        for (position, normal) in vertices.iter().zip(smoothed.iter()) {
+            // Inside the flat top every face points up
+            if *position == [1f32, 1f32, 1f32] {
+                assert_eq!(*normal, [0f32, 1f32, 0f32]);
+            }
+            // The corner of the slab averages the up, X- and Z- faces
+            if *position == [0f32, 1f32, 0f32] {
+                let length = (normal[0] * normal[0] + normal[1] * normal[1] + normal[2] * normal[2]).sqrt();
+                assert!((length - 1f32).abs() < 1e-5);
+                for (axis, sign) in [-1f32, 1f32, -1f32].iter().enumerate() {
+                    assert!((normal[axis] - sign * corner).abs() < 1e-5, "{:?}", normal);
+                }
+            }
+        }
+
+        let flat = generate_mesh_with(&chunk, &[None; 6], &registry(), &MaterialAtlas::default(), &MeshConfig { smooth_normals : false, ..config })
+            .unwrap()
+            .opaque;
+        assert!(normals(&flat).iter().all(|normal| normal.iter().filter(|n| **n != 0f32).count() == 1));
+    }
+
+    #[test]
+    fn welding_shares_the_corners_of_neighboring_faces() {
+        let mut atlas = MaterialAtlas::default();
+        atlas.insert(STONE, UvRect::new([0.5f32, 0.5f32], [1f32, 1f32]));
+        let mut chunk = Chunk::<2, 1, 1>::empty();
+        chunk.set_block(0, 0, 0, Block { material : STONE });
+        chunk.set_block(1, 0, 0, Block { material : STONE });
+
+        let config = MeshConfig { greedy : false, ambient_occlusion : false, weld_vertices : true, ..MeshConfig::DEFAULT };
+        let welded = generate_mesh_with(&chunk, &[None; 6], &registry(), &atlas, &config)
+            .unwrap()
+            .opaque;
+        let separate = generate_mesh_with(&chunk, &[None; 6], &registry(), &atlas, &MeshConfig { weld_vertices : false, ..config })
+            .unwrap()
+            .opaque;
+
+        // The four faces along the X axis share the two corners on their common edge
+        assert_eq!(positions(&separate).len(), 40);
+        assert_eq!(positions(&welded).len(), 32);
+        assert_eq!(indices(&welded).len(), 60);
+
+        // Both meshes have the same triangles
+        let (welded_positions, welded_indices) = (positions(&welded), indices(&welded));
+        let (separate_positions, separate_indices) = (positions(&separate), indices(&separate));
+        for (welded_index, separate_index) in welded_indices.iter().zip(separate_indices.iter()) {
+            assert_eq!(welded_positions[*welded_index as usize], separate_positions[*separate_index as usize]);
+        }
+
+        // Every face still covers one block's worth of its tile
+        for triangle in welded_indices.chunks_exact(3) {
+            let uvs : Vec<[f32; 2]> = triangle.iter().map(|index| uvs(&welded)[*index as usize]).collect();
+            for axis in 0..2 {
+                let min = uvs.iter().map(|uv| uv[axis]).fold(f32::INFINITY, f32::min);
+                let max = uvs.iter().map(|uv| uv[axis]).fold(f32::NEG_INFINITY, f32::max);
+                assert_eq!(max - min, 0.5f32);
+            }
+        }
+    }
+
+    #[test]
+    fn registered_models_are_copied_to_each_block() {
+        let plant = MaterialID::new(5);
+        let mut registry = registry();
+        registry.register(Material { id : plant, transparent : false, custom_model : true, tint : WHITE });
+
+        // Two crossed quads
+        let mut model = Mesh::new(PrimitiveTopology::TriangleList);
+        model.set_attribute(Mesh::ATTRIBUTE_POSITION, vec![
+            [0f32, 0f32, 0f32], [0f32, 1f32, 0f32], [1f32, 1f32, 1f32], [1f32, 0f32, 1f32],
+            [1f32, 0f32, 0f32], [1f32, 1f32, 0f32], [0f32, 1f32, 1f32], [0f32, 0f32, 1f32],
+        ]);
+        model.set_attribute(Mesh::ATTRIBUTE_UV_0, vec![[0.25f32, 0.5f32]; 8]);
+        model.set_indices(Some(Indices::U16(vec![0, 1, 2, 2, 3, 0, 4, 5, 6, 6, 7, 4])));
+        registry.register_model(plant, model);
+
+        let mut chunk = Chunk16::empty();
+        chunk.set_block(2, 0, 0, Block { material : plant });
+        chunk.set_block(7, 3, 1, Block { material : plant });
+        let meshes = GreedyChunkMesher::generate_mesh(&chunk, &[None; 6], &registry, &MaterialAtlas::default()).unwrap();
+
+        // Blocks with a model aren't reported as custom model blocks
+        assert!(meshes.custom_models.is_empty());
+        let positions = positions(&meshes.opaque);
+        let indices = indices(&meshes.opaque);
+        assert_eq!(positions.len(), 16);
+        assert_eq!(indices.len(), 24);
+        assert_eq!(positions[0], [2f32, 0f32, 0f32]);
+        assert_eq!(positions[8], [7f32, 3f32, 1f32]);
+        assert_eq!(positions[10], [8f32, 4f32, 2f32]);
+        assert_eq!(indices[12], 8);
+        assert!(uvs(&meshes.opaque).iter().all(|uv| *uv == [0.25f32, 0.5f32]));
+
+        // The triangles of the models aren't faces of the mesher
+        assert_eq!(meshes.stats.faces_emitted, 0);
+        assert_eq!(meshes.stats.indices, 24);
+    }
+
+    #[test]
+    fn fill_and_fill_region() {
+        let mut chunk = Chunk16::empty();
+        chunk.clear_change();
+        chunk.fill(STONE);
+        assert!(chunk.is_full());
+        assert!(chunk.has_changed());
+
+        let mut chunk = Chunk16::empty();
+        chunk.clear_change();
+        // The box is clamped to the chunk
+        chunk.fill_region(point![2, 3, 4], point![3, 3, 20], GLASS);
+        for (x, y, z, block) in chunk.iter_blocks() {
+            let inside = (2..=3).contains(&x) && y == 3 && z >= 4;
+            assert_eq!(block.material == GLASS, inside, "{} {} {}", x, y, z);
+        }
+        assert_eq!(chunk.solid_count(), 2 * 12);
+        assert!(chunk.has_changed());
+        assert!(chunk.has_changed_edge(AADirection::ZPositive));
+        assert!(!chunk.has_changed_edge(AADirection::ZNegative));
+
+        // An empty box doesn't set anything
+        chunk.fill_region(point![5, 0, 0], point![4, 15, 15], STONE);
+        assert_eq!(chunk.solid_count(), 2 * 12);
+    }
+
+    #[test]
+    fn air_and_opaque_blocks() {
+        let stone = Block { material : STONE };
+        let glass = Block { material : GLASS };
+        assert!(Block::default().is_air());
+        assert!(!stone.is_air());
+        assert!(!glass.is_air());
+
+        assert!(stone.is_opaque(&registry()));
+        assert!(!glass.is_opaque(&registry()));
+        assert!(!Block::default().is_opaque(&registry()));
+    }
+
+    #[test]
+    fn changed_meshing_skips_untouched_chunks() {
+        let mut app = app();
+        let handle = add_mesh(&mut app);
+        let chunk = app.world.spawn().insert(solid_chunk()).insert(handle.clone()).id();
+
+        let mut stage = SystemStage::single_threaded();
+        stage.add_system(changed_chunk_meshing_system::<GreedyChunkMesher, 16, 16, 16>.system());
+        stage.run(&mut app.world);
+        assert_eq!(mesh_positions(&app, &handle).len(), 24);
+
+        // Without the end of tick system the chunk's flag is still set, but bevy didn't see
+        // it being modified
+        app.world.get_resource_mut::<Assets<Mesh>>().unwrap().set_untracked(&handle, Mesh::new(PrimitiveTopology::TriangleList));
+        assert!(app.world.get::<Chunk16>(chunk).unwrap().has_changed());
+        stage.run(&mut app.world);
+        assert!(app.world.get_resource::<Assets<Mesh>>().unwrap().get(&handle).unwrap().attribute(Mesh::ATTRIBUTE_POSITION).is_none());
+
+        app.world.get_mut::<Chunk16>(chunk).unwrap().set_change();
+        stage.run(&mut app.world);
+        assert_eq!(mesh_positions(&app, &handle).len(), 24);
+    }
+
+    #[test]
+    fn changed_meshing_retries_chunks_waiting_for_assets() {
+        let mut app = app();
+        let removed = add_mesh(&mut app);
+        let stale = removed.clone_weak();
+        app.world.get_resource_mut::<Assets<Mesh>>().unwrap().remove(&removed);
+        let waiting = app.world.spawn().insert(solid_chunk()).insert(stale.clone_weak()).id();
+
+        let mut stage = SystemStage::single_threaded();
+        stage.add_system(changed_chunk_meshing_system::<GreedyChunkMesher, 16, 16, 16>.system().label("meshing"));
+        stage.add_system(chunk_end_of_tick_system::<16, 16, 16>.system().after("meshing"));
+        stage.run(&mut app.world);
+        assert!(app.world.get::<Chunk16>(waiting).unwrap().has_changed());
+
+        // The asset shows up without the chunk being modified
+        app.world.get_resource_mut::<Assets<Mesh>>().unwrap().set_untracked(&stale, Mesh::new(PrimitiveTopology::TriangleList));
+        stage.run(&mut app.world);
+        assert_eq!(mesh_positions(&app, &stale).len(), 24);
+        assert!(!app.world.get::<Chunk16>(waiting).unwrap().has_changed());
+    }
+
+    #[test]
+    fn tangents_follow_the_u_axis() {
+        let tangents = |mesh : &Mesh| match mesh.attribute(Mesh::ATTRIBUTE_TANGENT) {
+            Some(VertexAttributeValues::Float4(tangents)) => tangents.clone(),
+            _ => panic!("mesh has no tangents"),
+        };
+        let config = MeshConfig { greedy : false, tangents : true, ..MeshConfig::DEFAULT };
+        let mesh = generate_mesh_with(&single_block_chunk(), &[None; 6], &registry(), &MaterialAtlas::default(), &config)
+            .unwrap()
+            .opaque;
+
+        let (positions, normals, uvs, tangents) = (positions(&mesh), normals(&mesh), uvs(&mesh), tangents(&mesh));
+        for face in 0..6 {
+            let top_left = face * 4;
+            // From the top left to the top right corner u increases
+            assert!(uvs[top_left + 3][0] > uvs[top_left][0]);
+            let along_u = sub(positions[top_left + 3], positions[top_left]);
+            let tangent = [tangents[top_left][0], tangents[top_left][1], tangents[top_left][2]];
+            assert_eq!(tangent, along_u);
+
+            let normal = normals[top_left];
+            assert_eq!((0..3).map(|axis| tangent[axis] * normal[axis]).sum::<f32>(), 0f32);
+
+            // The bitangent points from the top left to the bottom left corner, along v
+            let bitangent = cross(normal, tangent).map(|component| component * tangents[top_left][3]);
+            assert_eq!(bitangent, sub(positions[top_left + 1], positions[top_left]));
+        }
+
+        let untangented = generate_mesh_with(&single_block_chunk(), &[None; 6], &registry(), &MaterialAtlas::default(), &MeshConfig { tangents : false, ..config })
+            .unwrap()
+            .opaque;
+        assert!(untangented.attribute(Mesh::ATTRIBUTE_TANGENT).is_none());
+    }
+
+    #[test]
+    fn spawned_chunks_have_a_mesh() {
+        struct Spawned(Option<Entity>);
+        fn spawner(mut commands : Commands, mut meshes : ResMut<Assets<Mesh>>, mut spawned : ResMut<Spawned>) {
+            spawned.0 = Some(spawn_chunk(&mut commands, &mut meshes, solid_chunk()));
+        }
+
+        let mut app = app();
+        app.world.insert_resource(Spawned(None));
+        let mut stage = SystemStage::single_threaded();
+        stage.add_system(spawner.system());
+        stage.run(&mut app.world);
+
+        let entity = app.world.get_resource::<Spawned>().unwrap().0.unwrap();
+        assert!(app.world.get::<Chunk16>(entity).is_some());
+        let handle = app.world.get::<Handle<Mesh>>(entity).unwrap().clone();
+        assert!(app.world.get_resource::<Assets<Mesh>>().unwrap().get(&handle).is_some());
+
+        // The chunk is meshed on the first run of the meshing system
+        let mut stage = SystemStage::single_threaded();
+        stage.add_system(chunk_meshing_system::<NaiveChunkMesher, 16, 16, 16>.system());
+        stage.run(&mut app.world);
+        assert_eq!(mesh_positions(&app, &handle).len(), 6 * 16 * 16 * 4);
+    }
+
+    #[test]
+    fn paletted_blocks_round_trip() {
+        let mut chunk = Chunk16::empty();
+        for (x, y, z, block) in chunk.iter_blocks_mut() {
+            block.material = MaterialID::new(((x * 7 + y * 3 + z) % 5) as u32);
+        }
+
+        // Five materials fit into three bits per block
+        let paletted = PalettedBlocks::from_chunk(&chunk);
+        assert_eq!(paletted.bits_per_block(), 3);
+        assert!(paletted.memory_usage() < std::mem::size_of::<Block>() * 16 * 16 * 16 / 4);
+        let unpacked = paletted.to_chunk();
+        for (x, y, z, block) in chunk.iter_blocks() {
+            assert_eq!(paletted.block_at(x, y, z).material, block.material);
+            assert_eq!(unpacked.block_at(x, y, z).material, block.material);
+        }
+
+        // Setting blocks widens the indices as the palette grows
+        let mut paletted = PalettedBlocks::<16, 16, 16>::new();
+        let mut reference = Chunk16::empty();
+        for i in 0..300u32 {
+            let (x, y, z) = ((i * 13 % 16) as usize, (i * 7 % 16) as usize, (i % 16) as usize);
+            let material = MaterialID::new(i % 40);
+            assert_eq!(paletted.set_block(x, y, z, Block { material }).material, reference.block_at(x, y, z).material);
+            reference.set_block(x, y, z, Block { material });
+        }
+        assert_eq!(paletted.bits_per_block(), 6);
+        for (x, y, z, block) in reference.iter_blocks() {
+            assert_eq!(paletted.block_at(x, y, z).material, block.material);
+        }
+        assert_eq!(paletted.to_chunk().solid_count(), reference.solid_count());
+    }
+
+    #[test]
+    fn raycasts_hit_the_faces_towards_the_ray() {
+        let mut chunk = Chunk16::empty();
+        chunk.set_block(8, 8, 8, Block { material : STONE });
+
+        let cases = [
+            (point![0.5f32, 8.5, 8.5], vector![1f32, 0., 0.], AADirection::XNegative, 7.5f32),
+            (point![15.5f32, 8.5, 8.5], vector![-1f32, 0., 0.], AADirection::XPositive, 6.5f32),
+            (point![8.5f32, 0.5, 8.5], vector![0f32, 1., 0.], AADirection::YNegative, 7.5f32),
+            (point![8.5f32, 15.5, 8.5], vector![0f32, -2., 0.], AADirection::YPositive, 6.5f32),
+            (point![8.5f32, 8.5, -3.5], vector![0f32, 0., 1.], AADirection::ZNegative, 11.5f32),
+            (point![8.5f32, 8.5, 20.], vector![0f32, 0., -1.], AADirection::ZPositive, 11f32),
+        ];
+        for (origin, direction, face, distance) in cases.iter() {
+            let hit = raycast(&chunk, *origin, *direction, 100f32).unwrap();
+            assert_eq!(hit.block, point![8, 8, 8]);
+            assert_eq!(hit.face, *face);
+            assert!((hit.distance - distance).abs() < 1e-4, "{} {}", hit.distance, distance);
+        }
+
+        // Missing the block, stopping short of it and pointing away from the chunk
+        assert!(raycast(&chunk, point![0.5, 0.5, 0.5], vector![1., 0., 0.], 100f32).is_none());
+        assert!(raycast(&chunk, point![0.5, 8.5, 8.5], vector![1., 0., 0.], 5f32).is_none());
+        assert!(raycast(&chunk, point![-5., 8.5, 8.5], vector![-1., 0., 0.], f32::INFINITY).is_none());
+        assert!(raycast(&chunk, point![0.5, 0.5, 0.5], vector![1., 1., 1.], f32::INFINITY).is_some());
+    }
+
+    #[test]
+    fn blocks_across_the_edge_occlude_corners() {
+        let mut chunk = Chunk16::empty();
+        chunk.set_block(15, 0, 8, Block { material : STONE });
+        // Above the top of `chunk`'s block, across the X+ edge
+        let mut neighbor = Chunk16::empty();
+        neighbor.set_block(0, 1, 8, Block { material : STONE });
+
+        let config = MeshConfig { greedy : false, ..MeshConfig::DEFAULT };
+        let mut neighbors : ChunkNeighborhood<16, 16, 16> = [None; 6];
+        let open = generate_mesh_with(&chunk, &neighbors, &registry(), &MaterialAtlas::default(), &config)
+            .unwrap()
+            .opaque;
+        neighbors[AADirection::XPositive as usize] = Some(&neighbor);
+        let occluded = generate_mesh_with(&chunk, &neighbors, &registry(), &MaterialAtlas::default(), &config)
+            .unwrap()
+            .opaque;
+
+        let (vertices, up, open_colors, occluded_colors) = (positions(&occluded), normals(&occluded), colors(&open), colors(&occluded));
+        let darker : Vec<usize> = (0..vertices.len())
+            .filter(|i| up[*i] == [0f32, 1f32, 0f32] && vertices[*i][0] == 16f32)
+            .collect();
+        assert_eq!(darker.len(), 2);
+        for i in darker {
+            assert!(occluded_colors[i][0] < open_colors[i][0]);
+        }
+    }
+
+    #[test]
+    fn unknown_materials_fail_without_a_fallback() {
+        let unknown = MaterialID::new(999);
+        let mut chunk = Chunk16::empty();
+        chunk.set_block(1, 1, 1, Block { material : STONE });
+        chunk.set_block(2, 1, 1, Block { material : unknown });
+
+        let mut strict = registry();
+        strict.set_fallback(None);
+        let result = NaiveChunkMesher::generate_mesh(&chunk, &[None; 6], &strict, &MaterialAtlas::default());
+        assert_eq!(result.err(), Some(MeshError::UnknownMaterial(unknown)));
+        let result = GreedyChunkMesher::generate_mesh(&chunk, &[None; 6], &strict, &MaterialAtlas::default());
+        assert_eq!(result.err(), Some(MeshError::UnknownMaterial(unknown)));
+    }
+
+    #[test]
+    fn unknown_materials_mesh_like_the_fallback() {
+        let unknown = MaterialID::new(999);
+        let mut chunk = Chunk16::empty();
+        chunk.set_block(1, 1, 1, Block { material : STONE });
+        chunk.set_block(2, 1, 1, Block { material : unknown });
+        let is_magenta = |color : &&[f32; 4]| color[0] > 0f32 && color[1] == 0f32 && color[2] > 0f32;
+
+        // The default fallback is opaque and magenta
+        assert_eq!(registry().fallback(), Some(&MISSING));
+        let meshes = NaiveChunkMesher::generate_mesh(&chunk, &[None; 6], &registry(), &MaterialAtlas::default())
+            .unwrap();
+        assert_eq!(positions(&meshes.opaque).len(), 2 * 5 * 4);
+        assert_eq!(colors(&meshes.opaque).iter().filter(is_magenta).count(), 5 * 4);
+        assert!(positions(&meshes.transparent).is_empty());
+
+        // A transparent fallback moves the unknown block into the transparent mesh, tinted
+        // like the fallback. It no longer hides the face of the stone block beside it, while
+        // the stone still hides the unknown block's face.
+        let tint = [0f32, 0.5f32, 1f32, 0.5f32];
+        let mut glassy = registry();
+        glassy.set_fallback(Some(Material { id : MISSING.id, transparent : true, custom_model : false, tint }));
+        let meshes = NaiveChunkMesher::generate_mesh(&chunk, &[None; 6], &glassy, &MaterialAtlas::default())
+            .unwrap();
+        assert_eq!(positions(&meshes.opaque).len(), 6 * 4);
+        assert_eq!(positions(&meshes.transparent).len(), 5 * 4);
+        assert!(colors(&meshes.opaque).iter().all(|color| color[0] == color[1] && color[1] == color[2]));
+        for color in colors(&meshes.transparent) {
+            assert_eq!(color[3], tint[3]);
+            assert!(color[0] == 0f32 && color[1] < color[2]);
+        }
+    }
+
+    #[test]
+    fn chunks_that_fail_to_mesh_stay_dirty() {
+        let unknown = MaterialID::new(999);
+        let mut app = app();
+        app.world.get_resource_mut::<MaterialRegistry>().unwrap().set_fallback(None);
+        let handle = add_mesh(&mut app);
+        let mut chunk = Chunk16::empty();
+        chunk.set_block(0, 0, 0, Block { material : unknown });
+        let entity = app.world.spawn().insert(chunk).insert(handle.clone()).id();
+
+        let mut stage = SystemStage::single_threaded();
+        stage.add_system(chunk_meshing_system::<GreedyChunkMesher, 16, 16, 16>.system().label("meshing"));
+        stage.add_system(chunk_end_of_tick_system::<16, 16, 16>.system().after("meshing"));
+        stage.run(&mut app.world);
+        assert!(app.world.get::<Chunk16>(entity).unwrap().has_changed());
+
+        let material = Material { id : unknown, transparent : false, custom_model : false, tint : WHITE };
+        app.world.get_resource_mut::<MaterialRegistry>().unwrap().register(material);
+        stage.run(&mut app.world);
+        assert!(!app.world.get::<Chunk16>(entity).unwrap().has_changed());
+        assert_eq!(mesh_positions(&app, &handle).len(), 24);
+    }
+
+    #[test]
+    fn wireframes_outline_the_visible_faces() {
+        let mut chunk = single_block_chunk();
+        let wireframe = generate_wireframe(&chunk, &[None; 6], &registry(), &MeshConfig::DEFAULT).unwrap();
+        assert_eq!(indices(&wireframe).len(), 6 * 4 * 2);
+        assert!(wireframe.attribute(Mesh::ATTRIBUTE_NORMAL).is_none());
+
+        // The faces between the two blocks are culled
+        chunk.set_block(4, 4, 5, Block { material : STONE });
+        let naive = generate_wireframe(&chunk, &[None; 6], &registry(), &MeshConfig { greedy : false, ..MeshConfig::DEFAULT }).unwrap();
+        assert_eq!(indices(&naive).len(), 10 * 4 * 2);
+        let greedy = generate_wireframe(&chunk, &[None; 6], &registry(), &MeshConfig::DEFAULT).unwrap();
+        assert_eq!(indices(&greedy).len(), 6 * 4 * 2);
+    }
+
+    #[test]
+    fn clockwise_winding_flips_every_triangle() {
+        let counter_clockwise = generate_mesh_with(&single_block_chunk(), &[None; 6], &registry(), &MaterialAtlas::default(), &MeshConfig::DEFAULT)
+            .unwrap()
+            .opaque;
+        let clockwise = generate_mesh_with(&single_block_chunk(), &[None; 6], &registry(), &MaterialAtlas::default(), &MeshConfig { winding : Winding::Clockwise, ..MeshConfig::DEFAULT })
+            .unwrap()
+            .opaque;
+
+        let (a, b) = (indices(&counter_clockwise), indices(&clockwise));
+        assert_eq!(a.len(), b.len());
+        for (a, b) in a.chunks_exact(3).zip(b.chunks_exact(3)) {
+            assert_eq!([a[0], a[2], a[1]], [b[0], b[1], b[2]]);
+        }
+        assert_eq!(positions(&counter_clockwise), positions(&clockwise));
+    }
+
+    #[test]
+    fn offsets_move_every_vertex() {
+        let mut chunk = single_block_chunk();
+        chunk.set_block(15, 0, 7, Block { material : GLASS });
+        let config = MeshConfig { offset : vector![16f32, 0f32, 0f32], ..MeshConfig::DEFAULT };
+        let base = generate_mesh_with(&chunk, &[None; 6], &registry(), &MaterialAtlas::default(), &MeshConfig::DEFAULT).unwrap();
+        let moved = generate_mesh_with(&chunk, &[None; 6], &registry(), &MaterialAtlas::default(), &config).unwrap();
+
+        for (base, moved) in [(&base.opaque, &moved.opaque), (&base.transparent, &moved.transparent)].iter() {
+            let (before, after) = (positions(base), positions(moved));
+            assert!(!before.is_empty());
+            assert_eq!(before.len(), after.len());
+            for (before, after) in before.iter().zip(after.iter()) {
+                assert_eq!([before[0] + 16f32, before[1], before[2]], *after);
+            }
+            assert_eq!(indices(base), indices(moved));
+        }
+    }
+
+    #[test]
+    fn incremental_updates_only_touch_the_edited_blocks() {
+        let config = MeshConfig { greedy : false, ..MeshConfig::DEFAULT };
+        let mut chunk = Chunk16::empty();
+        for z in 0..16 {
+            for x in 0..16 {
+                for y in 0..(x + z) % 5 {
+                    chunk.set_block(x, y, z, Block { material : [STONE, GLASS][x % 2] });
+                }
+            }
+        }
+        let mut mesh = IncrementalChunkMesh::new(&chunk, &[None; 6], &registry(), &MaterialAtlas::default(), &config).unwrap();
+        chunk.clear_change();
+        assert!(chunk.dirty_region().is_none());
+        let before = mesh.opaque().positions.clone();
+
+        chunk.set_block(8, 6, 8, Block { material : STONE });
+        chunk.set_block(8, 0, 8, Block::default());
+        assert_eq!(chunk.dirty_region(), Some((point![8, 0, 8], point![8, 6, 8])));
+        mesh.update(&chunk, &[None; 6], &registry(), &MaterialAtlas::default(), &config).unwrap();
+
+        // The same quads as meshing the chunk from scratch
+        let fresh = generate_mesh_with(&chunk, &[None; 6], &registry(), &MaterialAtlas::default(), &config)
+            .unwrap();
+        let quads = |mesh : &Mesh| {
+            let (positions, colors) = (positions(mesh), colors(mesh));
+            let mut quads : Vec<String> = (0..positions.len() / 4)
+                .map(|quad| format!("{:?}{:?}", &positions[quad * 4..quad * 4 + 4], &colors[quad * 4..quad * 4 + 4]))
+                .collect();
+            quads.sort();
+            quads
+        };
+        let updated = mesh.to_meshes();
+        assert_eq!(quads(&updated.opaque), quads(&fresh.opaque));
+        assert_eq!(quads(&updated.transparent), quads(&fresh.transparent));
+
+        // The quads of the first rows are far from the edits and stay in place
+        assert_eq!(mesh.opaque().positions[..40], before[..40]);
+        for (i, index) in mesh.opaque().indices.iter().enumerate() {
+            assert_eq!(*index as usize / 4, i / 6);
+        }
+    }
+
+    #[test]
+    fn incremental_updates_apply_tangents_winding_and_offset() {
+        let config = MeshConfig {
+            greedy : false,
+            tangents : true,
+            winding : Winding::Clockwise,
+            offset : Vector3::new(16f32, 0f32, -16f32),
+            ..MeshConfig::DEFAULT
+        };
+        let mut chunk = Chunk16::empty();
+        chunk.fill_region(point![0, 0, 0], point![15, 2, 15], STONE);
+        let mut mesh = IncrementalChunkMesh::new(&chunk, &[None; 6], &registry(), &MaterialAtlas::default(), &config).unwrap();
+        chunk.clear_change();
+
+        chunk.set_block(4, 2, 4, Block { material : GLASS });
+        chunk.set_block(9, 1, 3, Block::default());
+        mesh.update(&chunk, &[None; 6], &registry(), &MaterialAtlas::default(), &config).unwrap();
+
+        // Every quad with its corners, tangents and triangles relative to its first vertex
+        let quads = |mesh : &Mesh| {
+            let tangents = match mesh.attribute(Mesh::ATTRIBUTE_TANGENT) {
+                Some(VertexAttributeValues::Float4(tangents)) => tangents.clone(),
+                _ => panic!("mesh has no tangents"),
+            };
+            let (positions, indices) = (positions(mesh), indices(mesh));
+            assert_eq!(tangents.len(), positions.len());
+            let mut quads : Vec<String> = (0..positions.len() / 4)
+                .map(|quad| {
+                    let corners : Vec<u32> = indices[quad * 6..quad * 6 + 6].iter().map(|index| index - quad as u32 * 4).collect();
+                    format!("{:?}{:?}{:?}", &positions[quad * 4..quad * 4 + 4], &tangents[quad * 4..quad * 4 + 4], corners)
+                })
+                .collect();
+            quads.sort();
+            quads
+        };
+        let fresh = generate_mesh_with(&chunk, &[None; 6], &registry(), &MaterialAtlas::default(), &config).unwrap();
+        let updated = mesh.to_meshes();
+        assert_eq!(quads(&updated.opaque), quads(&fresh.opaque));
+        assert_eq!(quads(&updated.transparent), quads(&fresh.transparent));
+        assert_eq!(updated.stats.faces_emitted, fresh.stats.faces_emitted);
+    }
+
+    #[test]
+    fn count_materials() {
+        let mut chunk = Chunk16::empty();
+        chunk.fill_region(point![0, 0, 0], point![15, 3, 15], STONE);
+        chunk.fill_region(point![0, 4, 0], point![3, 4, 3], GLASS);
+        assert_eq!(chunk.count_material(STONE), 16 * 16 * 4);
+        assert_eq!(chunk.count_material(GLASS), 16);
+        assert_eq!(chunk.count_material(AIR.id), 16 * 16 * 16 - 16 * 16 * 4 - 16);
+        assert_eq!(chunk.count_material(MaterialID::new(7)), 0);
+
+        let paletted = PalettedBlocks::from_chunk(&chunk);
+        assert_eq!(paletted.count_material(STONE), 16 * 16 * 4);
+        assert_eq!(paletted.count_material(GLASS), 16);
+        assert_eq!(paletted.count_material(MaterialID::new(7)), 0);
+    }
+
+    #[test]
+    fn tall_chunk_face_counts() {
+        type TallChunk = Chunk<16, 64, 16>;
+
+        for index in 0..16 * 64 * 16 {
+            let (x, y, z) = TallChunk::coords(index);
+            assert!(x < 16 && y < 64 && z < 16);
+            assert_eq!(TallChunk::index(x, y, z), index);
+        }
+
+        let mut chunk = TallChunk::empty();
+        chunk.fill(STONE);
+        let mesh = NaiveChunkMesher::generate_mesh(&chunk, &[None; 6], &registry(), &MaterialAtlas::default())
+            .unwrap()
+            .opaque;
+
+        // The top and bottom keep their 16×16 faces, the sides have four times as many as
+        // those of a 16×16×16 chunk
+        assert_eq!(faces_towards(&mesh, [0f32, 1f32, 0f32]), 16 * 16);
+        assert_eq!(faces_towards(&mesh, [0f32, -1f32, 0f32]), 16 * 16);
+        assert_eq!(faces_towards(&mesh, [1f32, 0f32, 0f32]), 16 * 64);
+        assert_eq!(faces_towards(&mesh, [-1f32, 0f32, 0f32]), 16 * 64);
+        assert_eq!(faces_towards(&mesh, [0f32, 0f32, 1f32]), 16 * 64);
+        assert_eq!(faces_towards(&mesh, [0f32, 0f32, -1f32]), 16 * 64);
+        assert_eq!(positions(&mesh).len(), (2 * 16 * 16 + 4 * 16 * 64) * 4);
+        assert!(positions(&mesh).iter().all(|p| p[1] >= 0f32 && p[1] <= 64f32));
+    }
+
+    #[test]
+    fn material_presets() {
+        let solid = Material::solid(MaterialID::new(3));
+        assert_eq!(solid.id, MaterialID::new(3));
+        assert!(!solid.transparent);
+        assert!(!solid.custom_model);
+        assert_eq!(solid.tint, WHITE);
+
+        let transparent = Material::transparent(MaterialID::new(4));
+        assert!(transparent.transparent);
+        assert!(!transparent.custom_model);
+        assert_eq!(transparent.tint, WHITE);
+
+        let mut registry = MaterialRegistry::default();
+        registry.register(solid);
+        registry.register(transparent);
+        assert!(registry.is_solid(MaterialID::new(3)));
+        assert!(!registry.is_solid(MaterialID::new(4)));
+    }
+
+    #[test]
+    fn air_cant_be_registered() {
+        let mut registry = MaterialRegistry::default();
+        assert!(!registry.register(Material::solid(AIR.id)));
+        assert_eq!(registry.get(AIR.id), Some(&AIR));
+        assert!(!registry.is_solid(AIR.id));
+        assert!(registry.register(Material::solid(STONE)));
+    }
+
+    #[test]
+    fn bounds_cover_the_vertices() {
+        let mut chunk = Chunk16::empty();
+        chunk.set_block(0, 0, 0, Block { material : STONE });
+        let meshes = GreedyChunkMesher::generate_mesh(&chunk, &[None; 6], &registry(), &MaterialAtlas::default())
+            .unwrap();
+        assert_eq!(meshes.bounds, MeshBounds { min : point![0f32, 0f32, 0f32], max : point![1f32, 1f32, 1f32] });
+        assert!(!meshes.bounds.is_empty());
+
+        // Without vertices the bounds are empty instead of a point at the origin
+        let empty = GreedyChunkMesher::generate_mesh(&Chunk16::empty(), &[None; 6], &registry(), &MaterialAtlas::default())
+            .unwrap();
+        assert!(empty.bounds.is_empty());
+        assert_eq!(empty.bounds.union(&meshes.bounds), meshes.bounds);
+    }
+
+    #[test]
+    fn removing_neighbors() {
+        let mut world = World::new();
+        let (a, b) = (world.spawn().id(), world.spawn().id());
+        let mut chunk = Chunk16::empty();
+        chunk.set_neighbor(a, AADirection::XPositive);
+        chunk.set_neighbor(b, AADirection::YNegative);
+
+        assert_eq!(chunk.remove_neighbor(AADirection::XPositive), Some(a));
+        assert_eq!(chunk.get_neighbor(AADirection::XPositive), None);
+        assert_eq!(chunk.remove_neighbor(AADirection::XPositive), None);
+        assert_eq!(chunk.get_neighbor(AADirection::YNegative), Some(b));
+        chunk.clear_neighbors();
+        assert!(chunk.neighbors().iter().all(|neighbor| neighbor.is_none()));
+    }
+
+    #[test]
+    fn despawned_neighbors_are_treated_as_air() {
+        let mut app = app();
+        let handle = add_mesh(&mut app);
+        let chunk = app.world.spawn().insert(solid_chunk()).insert(handle.clone()).id();
+        let neighbor = app.world.spawn().insert(solid_chunk()).id();
+        assert!(link_chunks::<16, 16, 16>(&mut app.world, chunk, neighbor, AADirection::XPositive));
+        app.world.despawn(neighbor);
+
+        let mut stage = SystemStage::single_threaded();
+        stage.add_system(chunk_meshing_system::<GreedyChunkMesher, 16, 16, 16>.system());
+        stage.run(&mut app.world);
+        assert_eq!(mesh_positions(&app, &handle).len(), 6 * 4);
+    }
+
+    #[test]
+    fn light_levels_darken_faces() {
+        let mut chunk = Chunk16::empty();
+        chunk.fill_region(point![0, 0, 0], point![15, 0, 15], STONE);
+        let config = MeshConfig { greedy : false, ambient_occlusion : false, ..MeshConfig::DEFAULT };
+        let lit = generate_mesh_with(&chunk, &[None; 6], &registry(), &MaterialAtlas::default(), &config)
+            .unwrap()
+            .opaque;
+        assert!(colors(&lit).iter().all(|color| color[0] == 1f32));
+
+        // Darken the air above one half of the floor
+        for z in 0..16 {
+            for x in 0..8 {
+                chunk.set_light(x, 1, z, 3);
+            }
+        }
+        assert_eq!(chunk.light_at(2, 1, 2), 3);
+        assert_eq!(chunk.light_at(9, 1, 2), MAX_LIGHT);
+
+        let mesh = generate_mesh_with(&chunk, &[None; 6], &registry(), &MaterialAtlas::default(), &config)
+            .unwrap()
+            .opaque;
+        let (vertices, up, brightness) = (positions(&mesh), normals(&mesh), colors(&mesh));
+        let top = |x : f32, z : f32| (0..vertices.len())
+            .filter(|i| up[*i] == [0f32, 1f32, 0f32] && vertices[*i][0] == x && vertices[*i][2] == z)
+            .map(|i| brightness[i][0])
+            .fold(f32::MAX, f32::min);
+        assert!((top(2f32, 5f32) - 3f32 / 15f32).abs() < 1e-5);
+        assert_eq!(top(12f32, 5f32), 1f32);
+        // Corners on the border between the halves are in between
+        assert!(top(8f32, 5f32) > 3f32 / 15f32 && top(8f32, 5f32) < 1f32);
+
+        chunk.clear_light();
+        assert_eq!(chunk.light_at(2, 1, 2), MAX_LIGHT);
+    }
+
+    #[test]
+    fn light_falls_off_with_distance() {
+        let mut chunk = Chunk16::empty();
+        propagate_light(&mut chunk, &registry(), &[(8, 8, 8, 12)]);
+        for (x, y, z, _) in Chunk16::empty().iter_blocks() {
+            let distance = (x as i32 - 8).abs() + (y as i32 - 8).abs() + (z as i32 - 8).abs();
+            assert_eq!(chunk.light_at(x, y, z) as i32, (12 - distance).max(0), "{} {} {}", x, y, z);
+        }
+
+        // Opaque blocks stop the light
+        let mut chunk = Chunk16::empty();
+        chunk.fill_region(point![5, 0, 0], point![5, 15, 15], STONE);
+        propagate_light(&mut chunk, &registry(), &[(2, 8, 8, 15)]);
+        assert_eq!(chunk.light_at(4, 8, 8), 13);
+        assert_eq!(chunk.light_at(5, 8, 8), 0);
+        assert_eq!(chunk.light_at(6, 8, 8), 0);
+    }
+
+    #[test]
+    fn light_changes_only_mark_the_edges_they_reach() {
+        let mut chunk = Chunk16::empty();
+        propagate_light(&mut chunk, &registry(), &[(8, 8, 8, 3)]);
+        assert!(chunk.has_changed());
+        chunk.clear_change();
+        chunk.changed_edges = [false; 6];
+
+        // The same light again changes nothing
+        propagate_light(&mut chunk, &registry(), &[(8, 8, 8, 3)]);
+        assert!(!chunk.has_changed());
+
+        // Brighter light inside the chunk doesn't reach the neighbors
+        propagate_light(&mut chunk, &registry(), &[(8, 8, 8, 4)]);
+        assert!(chunk.has_changed());
+        assert_eq!(chunk.dirty_region(), Some((point![5, 5, 5], point![11, 11, 11])));
+        assert!(AADirection::iter().all(|direction| !chunk.has_changed_edge(direction)));
+
+        propagate_light(&mut chunk, &registry(), &[(8, 8, 8, 4), (14, 8, 8, 3)]);
+        assert!(chunk.has_changed_edge(AADirection::XPositive));
+        assert!(!chunk.has_changed_edge(AADirection::XNegative));
+    }
+
+    #[test]
+    fn clones_drop_the_neighbors() {
+        let mut world = World::new();
+        let neighbor = world.spawn().id();
+        let mut chunk = solid_chunk();
+        chunk.set_block(1, 2, 3, Block { material : GLASS });
+        chunk.set_neighbor(neighbor, AADirection::ZNegative);
+
+        let clone = chunk.clone();
+        for (x, y, z, block) in chunk.iter_blocks() {
+            assert_eq!(clone.block_at(x, y, z).material, block.material);
+        }
+        assert!(clone.neighbors().iter().all(|neighbor| neighbor.is_none()));
+        assert_eq!(clone.has_changed(), chunk.has_changed());
+        assert_eq!(clone.solid_count(), 16 * 16 * 16);
+        assert_eq!(chunk.get_neighbor(AADirection::ZNegative), Some(neighbor));
+    }
+
+    fn async_stage() -> SystemStage {
+        let mut stage = SystemStage::single_threaded();
+        stage.add_system(async_chunk_mesh_apply_system::<16, 16, 16>.system().label("apply"));
+        stage.add_system(async_chunk_meshing_system::<GreedyChunkMesher, 16, 16, 16>.system().label("spawn").after("apply"));
+        stage.add_system(chunk_end_of_tick_system::<16, 16, 16>.system().after("spawn"));
+        stage
+    }
+
+    fn run_until(app : &mut App, stage : &mut SystemStage, done : impl Fn(&App) -> bool) {
+        for _ in 0..1000 {
+            stage.run(&mut app.world);
+            if done(app) {
+                return;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(2));
+        }
+        panic!("meshing didn't finish");
+    }
+
+    #[test]
+    fn async_meshing_applies_finished_meshes() {
+        let mut app = app();
+        app.update();
+        let (handle_a, handle_b) = (add_mesh(&mut app), add_mesh(&mut app));
+        let a = app.world.spawn().insert(solid_chunk()).insert(handle_a.clone()).id();
+        let b = app.world.spawn().insert(solid_chunk()).insert(handle_b.clone()).id();
+        link_chunks::<16, 16, 16>(&mut app.world, a, b, AADirection::XPositive);
+
+        let mut stage = async_stage();
+        let idle = |app : &App, entity : Entity| {
+            app.world.get::<ChunkMeshTask>(entity).is_none() && !app.world.get::<Chunk16>(entity).unwrap().has_changed()
+        };
+        run_until(&mut app, &mut stage, |app| idle(app, a) && idle(app, b));
+
+        // The faces between the chunks are culled as in the synchronous systems
+        let expected = GreedyChunkMesher::generate_mesh(&solid_chunk(), &[None; 6], &registry(), &MaterialAtlas::default())
+            .unwrap();
+        assert_eq!(mesh_positions(&app, &handle_a).len(), positions(&expected.opaque).len() - 4);
+        assert_eq!(mesh_positions(&app, &handle_b).len(), positions(&expected.opaque).len() - 4);
+
+        // A result for a chunk that changed while it was meshed is dropped
+        app.world.get_mut::<Chunk16>(a).unwrap().set_block(0, 0, 0, Block::default());
+        stage.run(&mut app.world);
+        assert!(app.world.get::<ChunkMeshTask>(a).is_some());
+        app.world.get_mut::<Chunk16>(a).unwrap().set_block(0, 15, 0, Block::default());
+        run_until(&mut app, &mut stage, |app| idle(app, a));
+
+        let mut neighbors : ChunkNeighborhood<16, 16, 16> = [None; 6];
+        neighbors[AADirection::XPositive as usize] = app.world.get::<Chunk16>(b);
+        let current = GreedyChunkMesher::generate_mesh(app.world.get::<Chunk16>(a).unwrap(), &neighbors, &registry(), &MaterialAtlas::default())
+            .unwrap();
+        assert_eq!(mesh_positions(&app, &handle_a), positions(&current.opaque));
+    }
+
+    #[test]
+    fn async_meshing_gives_up_on_failing_chunks() {
+        let mut app = app();
+        app.world.get_resource_mut::<MaterialRegistry>().unwrap().set_fallback(None);
+        app.update();
+        let handle = add_mesh(&mut app);
+        let mut chunk = Chunk16::empty();
+        chunk.set_block(0, 0, 0, Block { material : MaterialID::new(999) });
+        let entity = app.world.spawn().insert(chunk).insert(handle.clone()).id();
+
+        // Each failed attempt leaves the chunk changed, until it was retried often enough
+        let mut stage = async_stage();
+        let idle = move |app : &App| {
+            app.world.get::<ChunkMeshTask>(entity).is_none() && !app.world.get::<Chunk16>(entity).unwrap().has_changed()
+        };
+        run_until(&mut app, &mut stage, idle);
+        assert!(app.world.get_resource::<Assets<Mesh>>().unwrap().get(&handle).unwrap().attribute(Mesh::ATTRIBUTE_POSITION).is_none());
+
+        stage.run(&mut app.world);
+        assert!(app.world.get::<ChunkMeshTask>(entity).is_none());
+
+        // Modifying the chunk queues it again
+        app.world.get_mut::<Chunk16>(entity).unwrap().set_block(0, 0, 0, Block::default());
+        stage.run(&mut app.world);
+        assert!(app.world.get::<ChunkMeshTask>(entity).is_some());
+        run_until(&mut app, &mut stage, idle);
+    }
+
+    #[test]
+    fn blocks_compare_by_material() {
+        assert_eq!(Block { material : STONE }, Block { material : STONE });
+        assert_ne!(Block { material : STONE }, Block { material : GLASS });
+        assert_ne!(Block { material : STONE }, Block::default());
+
+        // Light is stored in the chunk, next to the blocks
+        let mut chunk = Chunk16::empty();
+        chunk.set_light(0, 0, 0, 2);
+        assert_eq!(chunk.block_at(0, 0, 0), chunk.block_at(1, 0, 0));
+    }
+
+    #[test]
+    fn uv_inset_pulls_the_uvs_into_the_rect() {
+        let mut atlas = MaterialAtlas::default();
+        atlas.insert(STONE, UvRect::new([0.25f32, 0.5f32], [0.5f32, 0.75f32]));
+        atlas.set_inset(0.5f32, [256, 128]);
+        let mesh = NaiveChunkMesher::generate_mesh(&single_block_chunk(), &[None; 6], &registry(), &atlas)
+            .unwrap()
+            .opaque;
+
+        // Half a texel of a 256x128 atlas
+        let (du, dv) = (0.5f32 / 256f32, 0.5f32 / 128f32);
+        for face in uvs(&mesh).chunks_exact(4) {
+            let min = |axis : usize| face.iter().map(|uv| uv[axis]).fold(f32::INFINITY, f32::min);
+            let max = |axis : usize| face.iter().map(|uv| uv[axis]).fold(f32::NEG_INFINITY, f32::max);
+            assert_eq!([min(0), min(1)], [0.25f32 + du, 0.5f32 + dv]);
+            assert_eq!([max(0), max(1)], [0.5f32 - du, 0.75f32 - dv]);
+        }
+
+        // Materials without a rect are inset into the whole texture
+        assert_eq!(atlas.rect(GLASS, AADirection::XPositive), UvRect::new([du, dv], [1f32 - du, 1f32 - dv]));
+    }
+
+    #[test]
+    fn occlusion_splits_greedy_quads() {
+        let mesh_with = |chunk : &Chunk16, ambient_occlusion : bool| {
+            let config = MeshConfig { ambient_occlusion, ..MeshConfig::DEFAULT };
+            generate_mesh_with(chunk, &[None; 6], &registry(), &MaterialAtlas::default(), &config)
+                .unwrap()
+                .opaque
+        };
+        let mut chunk = Chunk16::empty();
+        chunk.fill_region(point![0, 0, 0], point![15, 0, 15], STONE);
+        assert_eq!(faces_towards(&mesh_with(&chunk, true), [0f32, 1f32, 0f32]), 1);
+
+        // A block on the floor darkens the corners around it, which can't be merged with the rest
+        chunk.set_block(8, 1, 8, Block { material : STONE });
+        let occluded = mesh_with(&chunk, true);
+        let flat = mesh_with(&chunk, false);
+        assert!(faces_towards(&occluded, [0f32, 1f32, 0f32]) > faces_towards(&flat, [0f32, 1f32, 0f32]));
+
+        let brightness = colors(&occluded);
+        for (index, (position, normal)) in positions(&occluded).iter().zip(normals(&occluded)).enumerate() {
+            if normal != [0f32, 1f32, 0f32] || position[1] != 1f32 {
+                continue;
+            }
+            let within = |min : f32, max : f32| (min..=max).contains(&position[0]) && (min..=max).contains(&position[2]);
+            if within(8f32, 9f32) {
+                assert!(brightness[index][0] < 1f32);
+            } else if !within(7f32, 10f32) {
+                assert_eq!(brightness[index][0], 1f32);
+            }
+        }
+    }
+
+    #[test]
+    fn neighbors_can_be_set_through_the_slice() {
+        let mut world = World::new();
+        let (above, behind) = (world.spawn().id(), world.spawn().id());
+        let mut chunk = Chunk16::empty();
+
+        let neighbors = chunk.neighbors_mut();
+        neighbors[AADirection::YPositive as usize] = Some(above);
+        neighbors[AADirection::ZNegative as usize] = Some(behind);
+
+        assert_eq!(chunk.get_neighbor(AADirection::YPositive), Some(above));
+        assert_eq!(chunk.get_neighbor(AADirection::ZNegative), Some(behind));
+        assert_eq!(chunk.get_neighbor(AADirection::XPositive), None);
+    }
+
+    #[test]
+    fn greedy_quads_stay_in_their_transparency_group() {
+        let mut chunk = Chunk::<4, 1, 1>::empty();
+        chunk.set_block(0, 0, 0, Block { material : STONE });
+        chunk.set_block(1, 0, 0, Block { material : STONE });
+        chunk.set_block(2, 0, 0, Block { material : GLASS });
+        chunk.set_block(3, 0, 0, Block { material : GLASS });
+
+        let meshes = GreedyChunkMesher::generate_mesh(&chunk, &[None; 6], &registry(), &MaterialAtlas::default())
+            .unwrap();
+
+        assert_eq!(faces_towards(&meshes.opaque, [0f32, 1f32, 0f32]), 1);
+        assert_eq!(faces_towards(&meshes.transparent, [0f32, 1f32, 0f32]), 1);
+        assert!(positions(&meshes.opaque).iter().all(|p| p[0] <= 2f32));
+        assert!(positions(&meshes.transparent).iter().all(|p| p[0] >= 2f32));
+    }
+
+    #[test]
+    fn topology_is_set_as_requested() {
+        let mesh_with = |topology : MeshTopology| {
+            let config = MeshConfig { topology, ..MeshConfig::DEFAULT };
+            generate_mesh_with(&single_block_chunk(), &[None; 6], &registry(), &MaterialAtlas::default(), &config)
+                .unwrap()
+                .opaque
+        };
+
+        // Two triangles with six indices into the four corners of each face
+        let list = mesh_with(MeshTopology::TriangleList);
+        assert_eq!(list.primitive_topology(), PrimitiveTopology::TriangleList);
+        assert_eq!(indices(&list).len(), 6 * 6);
+        for (face, face_indices) in indices(&list).chunks_exact(6).enumerate() {
+            assert!(face_indices.iter().all(|index| *index as usize / 4 == face));
+        }
+
+        let strip = mesh_with(MeshTopology::TriangleStrip);
+        assert_eq!(strip.primitive_topology(), PrimitiveTopology::TriangleStrip);
+        assert_eq!(positions(&strip), positions(&list));
+
+        let points = mesh_with(MeshTopology::PointList);
+        assert_eq!(points.primitive_topology(), PrimitiveTopology::PointList);
+        assert!(points.indices().is_none());
+    }
+
+    #[test]
+    fn empty_meshes_match_the_config() {
+        let config = MeshConfig {
+            tangents : true,
+            material_ids : true,
+            topology : MeshTopology::TriangleStrip,
+            ..MeshConfig::DEFAULT
+        };
+        let filled = generate_mesh_with(&single_block_chunk(), &[None; 6], &registry(), &MaterialAtlas::default(), &config)
+            .unwrap()
+            .opaque;
+        let empty = ChunkMeshes::empty(&config);
+
+        for mesh in [&empty.opaque, &empty.transparent].iter() {
+            assert_eq!(mesh.primitive_topology(), filled.primitive_topology());
+            for attribute in [Mesh::ATTRIBUTE_COLOR, Mesh::ATTRIBUTE_TANGENT, ATTRIBUTE_MATERIAL_ID].iter() {
+                assert!(filled.attribute(*attribute).is_some());
+                assert_eq!(mesh.attribute(*attribute).map(|values| values.len()), Some(0));
+            }
+        }
+    }
+
+    #[test]
+    fn merged_meshes_are_offset_by_their_position() {
+        let config = MeshConfig { greedy : false, ..MeshConfig::DEFAULT };
+        let mesh = IncrementalChunkMesh::new(&single_block_chunk(), &[None; 6], &registry(), &MaterialAtlas::default(), &config)
+            .unwrap();
+        let merged = merge_meshes(&[(point![0f32, 0f32, 0f32], mesh.opaque()), (point![16f32, 0f32, 0f32], mesh.opaque())]);
+
+        let vertices = positions(&merged);
+        assert_eq!(vertices.len(), 2 * 24);
+        assert_eq!(indices(&merged).len(), 2 * 36);
+        assert_eq!(indices(&merged).iter().max(), Some(&47));
+        for index in 0..24 {
+            assert_eq!(vertices[index + 24], [vertices[index][0] + 16f32, vertices[index][1], vertices[index][2]]);
+        }
+    }
+
+    #[test]
+    fn world_coordinates_reach_into_the_neighbors() {
+        let chunk = single_block_chunk();
+        let mut neighbor = Chunk16::empty();
+        neighbor.set_block(0, 4, 5, Block { material : GLASS });
+        let mut neighbors : ChunkNeighborhood<16, 16, 16> = [None; 6];
+
+        assert_eq!(chunk.get_block_world(&neighbors, 3, 4, 5).material, STONE);
+        assert!(chunk.get_block_world(&neighbors, 16, 4, 5).is_air());
+        assert!(chunk.get_block_world(&neighbors, -1, 4, 5).is_air());
+
+        neighbors[AADirection::XPositive as usize] = Some(&neighbor);
+        assert_eq!(chunk.get_block_world(&neighbors, 16, 4, 5).material, GLASS);
+        assert!(chunk.get_block_world(&neighbors, 16, 16, 5).is_air());
+    }
+
+    #[test]
+    fn greedy_output_is_reproducible() {
+        let mut chunk = Chunk::<8, 8, 8>::empty();
+        for index in 0..8 * 8 * 8 {
+            let (x, y, z) = Chunk::<8, 8, 8>::coords(index);
+            let material = [AIR.id, STONE, GLASS][(x * 7 + y * 3 + z * 5) % 3];
+            chunk.set_block(x, y, z, Block { material });
+        }
+
+        let registry = registry();
+        let atlas = MaterialAtlas::default();
+        let first = GreedyChunkMesher::generate_mesh(&chunk, &[None; 6], &registry, &atlas).unwrap();
+        let second = GreedyChunkMesher::generate_mesh(&chunk, &[None; 6], &registry, &atlas).unwrap();
+
+        assert!(!positions(&first.opaque).is_empty());
+        assert_eq!(positions(&first.opaque), positions(&second.opaque));
+        assert_eq!(indices(&first.opaque), indices(&second.opaque));
+        assert_eq!(positions(&first.transparent), positions(&second.transparent));
+        assert_eq!(indices(&first.transparent), indices(&second.transparent));
+    }
+
+    #[test]
+    fn sparse_chunks_mesh_like_dense_ones() {
+        let mut dense = Chunk16::empty();
+        let mut sparse = SparseChunk::<16, 16, 16>::new();
+        for (x, y, z, material) in [(0, 0, 0, STONE), (1, 0, 0, GLASS), (5, 9, 15, STONE)] {
+            dense.set_block(x, y, z, Block { material });
+            sparse.set_block(x, y, z, Block { material });
+        }
+        assert_eq!(sparse.solid_count(), 3);
+        for (x, y, z, block) in dense.iter_blocks() {
+            assert_eq!(sparse.block_at(x, y, z), *block);
+        }
+
+        let configs = [MeshConfig::DEFAULT, NAIVE_CONFIG, MeshConfig { lod : 1, ..MeshConfig::DEFAULT }];
+        for config in configs.iter() {
+            let expected = generate_mesh_with(&dense, &[None; 6], &registry(), &MaterialAtlas::default(), config).unwrap();
+            let actual = generate_mesh_with(&sparse, &[None; 6], &registry(), &MaterialAtlas::default(), config).unwrap();
+            for (expected, actual) in [(&expected.opaque, &actual.opaque), (&expected.transparent, &actual.transparent)] {
+                assert_eq!(positions(actual), positions(expected));
+                assert_eq!(indices(actual), indices(expected));
+                assert_eq!(colors(actual), colors(expected));
+            }
+        }
+
+        assert_eq!(SparseChunk::from_chunk(&dense).solid_count(), 3);
+        assert_eq!(sparse.to_chunk().solid_count(), 3);
+        sparse.set_block(0, 0, 0, Block::default());
+        assert_eq!(sparse.solid_count(), 2);
+    }
+
+    // A stone floor, without any of the optional storage methods
+    struct Floor;
+
+    impl BlockStorage<8, 8, 8> for Floor {
+        fn block_at(&self, _x : usize, y : usize, _z : usize) -> Block {
+            if y == 0 { Block { material : STONE } } else { Block::default() }
+        }
+    }
+
+    // Only the bottom layer of a 16x16x16 chunk, blocks above it would be out of range
+    struct Slab;
+
+    impl BlockStorage<16, 16, 16> for Slab {
+        fn block_at(&self, x : usize, y : usize, z : usize) -> Block {
+            assert!(x < 16 && y < 1 && z < 16, "Block index out of range");
+            Block { material : STONE }
+        }
+
+        fn size(&self) -> Vector3<usize> {
+            vector![16, 1, 16]
+        }
+    }
+
+    #[test]
+    fn custom_storages_mesh_through_the_generic_path() {
+        assert_eq!(Floor.size(), vector![8, 8, 8]);
+        assert!(AADirection::iter().all(|direction| Floor.get_neighbor(direction).is_none()));
+        assert_eq!(Floor.light_at(0, 0, 0), MAX_LIGHT);
+
+        let mesh = GreedyChunkMesher::generate_mesh(&Floor, &[None; 6], &registry(), &MaterialAtlas::default())
+            .unwrap()
+            .opaque;
+        assert_eq!(positions(&mesh).len(), 6 * 4);
+        assert_eq!(max_position(&mesh), [8f32, 1f32, 8f32]);
+
+        // Storages smaller than their chunk size are only read within their own size
+        let mesh = GreedyChunkMesher::generate_mesh(&Slab, &[None; 6], &registry(), &MaterialAtlas::default())
+            .unwrap()
+            .opaque;
+        assert_eq!(positions(&mesh).len(), 6 * 4);
+        assert_eq!(max_position(&mesh), [16f32, 1f32, 16f32]);
+        assert_eq!(positions(&generate_collision_mesh(&Slab, &[None; 6], &registry())).len(), 6 * 4);
+    }
+
+    #[test]
+    fn sweep_order_changes_the_floor_quads() {
+        // z = 1: X X X
+        // z = 0: . X .
+        let mut chunk = Chunk16::empty();
+        for (x, z) in [(1, 0), (0, 1), (1, 1), (2, 1)] {
+            chunk.set_block(x, 0, z, Block { material : STONE });
+        }
+        let mesh_with = |sweep_order : [Axis; 3]| {
+            let config = MeshConfig { ambient_occlusion : false, sweep_order, ..MeshConfig::DEFAULT };
+            generate_mesh_with(&chunk, &[None; 6], &registry(), &MaterialAtlas::default(), &config)
+                .unwrap()
+                .opaque
+        };
+        let x_first = mesh_with([Axis::X, Axis::Z, Axis::Y]);
+        let z_first = mesh_with([Axis::Z, Axis::X, Axis::Y]);
+        assert_eq!(faces_towards(&x_first, [0f32, 1f32, 0f32]), 3);
+        assert_eq!(faces_towards(&z_first, [0f32, 1f32, 0f32]), 2);
+
+        // Whatever the order, the quads of every direction stay in its plane and cover the same area
+        let area = |mesh : &Mesh, direction : AADirection| {
+            let vertices = positions(mesh);
+            let normal = direction.normal();
+            let mut area = 0f32;
+            for (quad, up) in vertices.chunks_exact(4).zip(normals(mesh).chunks_exact(4)) {
+                if up[0] != normal {
+                    continue;
+                }
+                let slice = |vertex : &[f32; 3]| vertex[direction.axis() as usize];
+                assert!(quad.iter().all(|vertex| slice(vertex) == slice(&quad[0])));
+                let spanned = cross(sub(quad[1], quad[0]), sub(quad[3], quad[0]));
+                area += spanned.iter().map(|extent| extent.abs()).sum::<f32>();
+            }
+            area
+        };
+        for direction in AADirection::iter() {
+            assert_eq!(area(&x_first, direction), area(&z_first, direction));
+        }
+        assert_eq!(area(&z_first, AADirection::YPositive), 4f32);
+    }
+
+    #[test]
+    fn boundary_layers_are_the_outermost_blocks() {
+        let mut chunk = Chunk::<4, 3, 2>::empty();
+        for (x, y, z, block) in chunk.iter_blocks_mut() {
+            block.material = MaterialID::new((1 + x + 10 * y + 100 * z) as u32);
+        }
+
+        let layer = chunk.boundary_layer(AADirection::XPositive);
+        assert_eq!(layer.len(), 3 * 2);
+        let mut expected : Vec<MaterialID> = (0..2)
+            .flat_map(|z| (0..3).map(move |y| (y, z)))
+            .map(|(y, z)| chunk.block_at(3, y, z).material)
+            .collect();
+        let mut actual = layer.clone();
+        expected.sort_by_key(|material| material.id);
+        actual.sort_by_key(|material| material.id);
+        assert_eq!(actual, expected);
+
+        let layer = chunk.boundary_layer(AADirection::YNegative);
+        assert_eq!(layer.len(), 4 * 2);
+        assert!(layer.iter().all(|material| (material.id - 1) / 10 % 10 == 0));
+    }
+
+    #[cfg(feature = "trace")]
+    mod trace {
+        use super::*;
+        use bevy::utils::tracing::{span, subscriber, Event, Metadata, Subscriber};
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        // Counts the `generate_mesh` spans and the values recorded into them
+        #[derive(Default)]
+        struct SpanCounter {
+            spans : Arc<AtomicUsize>,
+            records : Arc<AtomicUsize>,
+        }
+
+        impl Subscriber for SpanCounter {
+            fn enabled(&self, _metadata : &Metadata<'_>) -> bool {
+                true
+            }
+
+            fn new_span(&self, attributes : &span::Attributes<'_>) -> span::Id {
+                if attributes.metadata().name() == "generate_mesh" {
+                    self.spans.fetch_add(1, Ordering::SeqCst);
+                }
+                span::Id::from_u64(1)
+            }
+
+            fn record(&self, _span : &span::Id, _values : &span::Record<'_>) {
+                self.records.fetch_add(1, Ordering::SeqCst);
+            }
+
+            fn record_follows_from(&self, _span : &span::Id, _follows : &span::Id) {}
+
+            fn event(&self, _event : &Event<'_>) {}
+
+            fn enter(&self, _span : &span::Id) {}
+
+            fn exit(&self, _span : &span::Id) {}
+        }
+
+        #[test]
+        fn one_span_per_meshed_chunk() {
+            let counter = SpanCounter::default();
+            let (spans, records) = (counter.spans.clone(), counter.records.clone());
+
+            subscriber::with_default(counter, || {
+                let mut app = app();
+                let (dirty, clean) = (add_mesh(&mut app), add_mesh(&mut app));
+                app.world.spawn().insert(single_block_chunk()).insert(dirty);
+                let mut chunk = single_block_chunk();
+                chunk.clear_change();
+                app.world.spawn().insert(chunk).insert(clean);
+
+                let mut stage = SystemStage::single_threaded();
+                stage.add_system(chunk_meshing_system::<GreedyChunkMesher, 16, 16, 16>.system());
+                stage.run(&mut app.world);
+            });
+
+            // Only the dirty chunk is meshed, and its vertex count is recorded into the span
+            assert_eq!(spans.load(Ordering::SeqCst), 1);
+            assert_eq!(records.load(Ordering::SeqCst), 1);
+        }
+    }
+
+    #[test]
+    fn material_id_getter() {
+        assert_eq!(MaterialID::from(7u32).id(), 7);
+        assert_eq!(MaterialID::from(7u16).id(), 7);
+        assert_eq!(MaterialID::from(7u8).id(), 7);
+        assert_eq!(AIR.id.id(), 0);
+    }
+
+    #[test]
+    fn replacing_materials() {
+        let mut chunk = Chunk16::empty();
+        chunk.fill_region(point![4, 4, 4], point![7, 4, 4], STONE);
+        chunk.set_block(4, 5, 4, Block { material : GLASS });
+        chunk.clear_change();
+        chunk.changed_edges = [false; 6];
+
+        assert_eq!(chunk.replace_material(STONE, MaterialID::new(7)), 4);
+        assert_eq!(chunk.count_material(MaterialID::new(7)), 4);
+        assert_eq!(chunk.count_material(STONE), 0);
+        assert_eq!(chunk.block_at(4, 5, 4).material, GLASS);
+        assert_eq!(chunk.dirty_region(), Some((point![4, 4, 4], point![7, 4, 4])));
+
+        // Only replaced blocks on the outer layers mark the edges
+        assert!(AADirection::iter().all(|direction| !chunk.has_changed_edge(direction)));
+        chunk.set_block(15, 0, 8, Block { material : MaterialID::new(7) });
+        chunk.clear_change();
+        chunk.changed_edges = [false; 6];
+        assert_eq!(chunk.replace_material(MaterialID::new(7), AIR.id), 5);
+        assert!(chunk.has_changed_edge(AADirection::XPositive));
+        assert!(chunk.has_changed_edge(AADirection::YNegative));
+        assert!(!chunk.has_changed_edge(AADirection::XNegative));
+        assert_eq!(chunk.solid_count(), 1);
+
+        chunk.clear_change();
+        assert_eq!(chunk.replace_material(STONE, GLASS), 0);
+        assert!(!chunk.has_changed());
+    }
+
+    #[test]
+    fn replacing_paletted_materials_renames_the_palette() {
+        let mut chunk = Chunk16::empty();
+        chunk.fill_region(point![0, 0, 0], point![15, 0, 15], STONE);
+        chunk.set_block(3, 1, 3, Block { material : GLASS });
+        let mut paletted = PalettedBlocks::from_chunk(&chunk);
+
+        // Replacing with a material that is already in the palette keeps the blocks as they are
+        assert_eq!(paletted.replace_material(GLASS, STONE), 1);
+        assert_eq!(paletted.palette(), &[STONE, AIR.id, STONE]);
+        assert_eq!(paletted.bits_per_block(), 2);
+        assert_eq!(paletted.count_material(STONE), 16 * 16 + 1);
+        assert_eq!(paletted.count_material(GLASS), 0);
+        assert_eq!(paletted.block_at(3, 1, 3).material, STONE);
+
+        assert_eq!(paletted.replace_material(STONE, GLASS), 16 * 16 + 1);
+        assert_eq!(paletted.count_material(GLASS), 16 * 16 + 1);
+        paletted.set_block(0, 0, 0, Block::default());
+        assert_eq!(paletted.count_material(GLASS), 16 * 16);
+        assert_eq!(paletted.count_material(AIR.id), 16 * 16 * 16 - 16 * 16);
+        assert_eq!(paletted.to_chunk().count_material(GLASS), 16 * 16);
+        assert_eq!(paletted.replace_material(STONE, AIR.id), 0);
+    }
+
+    #[test]
+    fn reverted_chunks_hit_the_cache() {
+        let mut cache = ChunkMeshCache::<GreedyChunkMesher>::new(4);
+        let mut chunk = single_block_chunk();
+        let mesh = |cache : &mut ChunkMeshCache<GreedyChunkMesher>, chunk : &Chunk16| {
+            cache.generate_mesh(chunk, &[None; 6], &registry(), &MaterialAtlas::default())
+                .unwrap()
+                .opaque
+        };
+
+        let original = mesh(&mut cache, &chunk);
+        chunk.set_block(0, 0, 0, Block { material : STONE });
+        assert_eq!(positions(&mesh(&mut cache, &chunk)).len(), 2 * 6 * 4);
+        chunk.set_block(0, 0, 0, Block::default());
+        assert_eq!(positions(&mesh(&mut cache, &chunk)), positions(&original));
+        assert_eq!((cache.hits(), cache.misses(), cache.len()), (1, 2, 2));
+
+        // A neighbor is part of the state
+        let neighbor = solid_chunk();
+        cache.generate_mesh(&chunk, &[Some(&neighbor), None, None, None, None, None], &registry(), &MaterialAtlas::default())
+            .unwrap();
+        assert_eq!(cache.misses(), 3);
+    }
+
+    #[test]
+    fn cache_entries_are_compared_in_full() {
+        let mut cache = ChunkMeshCache::<GreedyChunkMesher>::new(2);
+        let (chunk, other) = (single_block_chunk(), solid_chunk());
+
+        // A different state stored under the chunk's hash isn't returned for it
+        let key = ChunkContent::new(&chunk, &[None; 6]).hash_key();
+        let meshes = GreedyChunkMesher::generate_mesh(&other, &[None; 6], &registry(), &MaterialAtlas::default()).unwrap();
+        cache.insert(key, ChunkContent::new(&other, &[None; 6]), meshes);
+        let meshes = cache.generate_mesh(&chunk, &[None; 6], &registry(), &MaterialAtlas::default()).unwrap();
+        assert_eq!(positions(&meshes.opaque).len(), 6 * 4);
+        assert_eq!((cache.hits(), cache.misses(), cache.len()), (0, 1, 1));
+
+        // The least recently used state is evicted
+        let mut third = single_block_chunk();
+        third.set_block(0, 0, 0, Block { material : STONE });
+        cache.generate_mesh(&other, &[None; 6], &registry(), &MaterialAtlas::default()).unwrap();
+        cache.generate_mesh(&chunk, &[None; 6], &registry(), &MaterialAtlas::default()).unwrap();
+        cache.generate_mesh(&third, &[None; 6], &registry(), &MaterialAtlas::default()).unwrap();
+        cache.generate_mesh(&chunk, &[None; 6], &registry(), &MaterialAtlas::default()).unwrap();
+        cache.generate_mesh(&other, &[None; 6], &registry(), &MaterialAtlas::default()).unwrap();
+        assert_eq!((cache.hits(), cache.misses(), cache.len()), (2, 4, 2));
+    }
+
+    #[test]
+    fn caching_meshers_share_their_cache() {
+        type Mesher = CachingChunkMesher<GreedyChunkMesher, 3>;
+        let chunk = single_block_chunk();
+
+        assert_eq!(Mesher::config(), GreedyChunkMesher::config());
+        let first = Mesher::generate_mesh(&chunk, &[None; 6], &registry(), &MaterialAtlas::default()).unwrap();
+        let mut buffers = ChunkMeshBuffers::new();
+        let second = Mesher::generate_mesh_with_buffers(&chunk, &[None; 6], &registry(), &MaterialAtlas::default(), &mut buffers)
+            .unwrap();
+        assert_eq!(positions(&first.opaque), positions(&second.opaque));
+        assert_eq!(Mesher::with_cache(|cache| (cache.hits(), cache.misses(), cache.len())), (1, 1, 1));
+
+        // Reduced meshes aren't cached
+        Mesher::generate_mesh_lod(&chunk, &[None; 6], &registry(), &MaterialAtlas::default(), 1).unwrap();
+        assert_eq!(Mesher::with_cache(|cache| cache.len()), 1);
+    }
+
+    #[test]
+    fn signed_coordinates() {
+        let chunk = single_block_chunk();
+        assert_eq!(chunk.block_at_signed(3, 4, 5).map(|block| block.material), Some(STONE));
+        assert!(chunk.block_at_signed(0, 0, 0).unwrap().is_air());
+        assert!(chunk.block_at_signed(-1, 4, 5).is_none());
+        assert!(chunk.block_at_signed(3, -1, 5).is_none());
+        assert!(chunk.block_at_signed(3, 4, 16).is_none());
+        assert!(chunk.block_at_signed(i32::MIN, i32::MAX, 0).is_none());
+    }
+
+    #[test]
+    fn faces_carry_their_material_id() {
+        let mut chunk = Chunk16::empty();
+        chunk.set_block(0, 0, 0, Block { material : STONE });
+        chunk.set_block(5, 5, 5, Block { material : MaterialID::new(3) });
+        let mut registry = registry();
+        registry.register(Material { id : MaterialID::new(3), transparent : false, custom_model : false, tint : WHITE });
+
+        let config = MeshConfig { material_ids : true, ..MeshConfig::DEFAULT };
+        let mesh = generate_mesh_with(&chunk, &[None; 6], &registry, &MaterialAtlas::default(), &config)
+            .unwrap()
+            .opaque;
+        let ids = match mesh.attribute(ATTRIBUTE_MATERIAL_ID) {
+            Some(VertexAttributeValues::Uint(ids)) => ids.clone(),
+            _ => panic!("Missing material ids"),
+        };
+        let vertices = positions(&mesh);
+        assert_eq!(ids.len(), vertices.len());
+        for (id, position) in ids.iter().zip(vertices.iter()) {
+            let expected = if position[0] <= 1f32 { STONE } else { MaterialID::new(3) };
+            assert_eq!(*id, expected.id());
+        }
+
+        let plain = generate_mesh_with(&chunk, &[None; 6], &registry, &MaterialAtlas::default(), &MeshConfig::DEFAULT)
+            .unwrap()
+            .opaque;
+        assert!(plain.attribute(ATTRIBUTE_MATERIAL_ID).is_none());
+    }
+
+    #[test]
+    fn floor_quads() {
+        let mut chunk = Chunk::<4, 2, 3>::empty();
+        chunk.fill_region(point![0, 0, 0], point![3, 0, 2], STONE);
+        let config = MeshConfig { ambient_occlusion : false, ..MeshConfig::DEFAULT };
+        let quads = greedy_face_quads(&chunk, &[None; 6], &registry(), &config).unwrap();
+
+        // One quad per side, the ones facing along Y span the whole floor
+        assert_eq!(quads.len(), 6);
+        let quad = |dir : AADirection| *quads.iter().find(|quad| quad.dir == dir).unwrap();
+        assert_eq!(quad(AADirection::YPositive), FaceQuad { pos : point![0, 0, 0], dir : AADirection::YPositive, size : vector![4, 3], material : STONE });
+        assert_eq!(quad(AADirection::YNegative), FaceQuad { pos : point![0, 0, 0], dir : AADirection::YNegative, size : vector![4, 3], material : STONE });
+        assert_eq!(quad(AADirection::XPositive).pos, point![3, 0, 0]);
+        assert_eq!(quad(AADirection::ZPositive).pos, point![0, 0, 2]);
+        for dir in AADirection::iter() {
+            let (u, v) = plane_axes(dir);
+            let size = quad(dir).size;
+            assert_eq!([size.x as usize, size.y as usize], [[4, 1, 3][u as usize], [4, 1, 3][v as usize]]);
+        }
+
+        // The vertices are generated from the quads
+        let mesh = generate_mesh_with(&chunk, &[None; 6], &registry(), &MaterialAtlas::default(), &config)
+            .unwrap()
+            .opaque;
+        assert_eq!(positions(&mesh).len(), quads.len() * 4);
+    }
+
+    #[test]
+    fn meshes_without_a_world() {
+        let mut registry = MaterialRegistry::new();
+        registry.register(Material { id : MaterialID::new(3), transparent : false, custom_model : false, tint : WHITE });
+
+        let mut chunk = Chunk::<16, 16, 16>::empty();
+        chunk.set_block(1, 1, 1, Block { material : MaterialID::new(3) });
+
+        let meshes = generate_mesh_with(&chunk, &[None; 6], &registry, &MaterialAtlas::default(), &MeshConfig::default())
+            .unwrap();
+        assert_eq!(positions(&meshes.opaque).len(), 6 * 4);
+        assert_eq!(indices(&meshes.opaque).len(), 6 * 6);
+        assert!(positions(&meshes.transparent).is_empty());
+    }
+
+    #[test]
+    fn diff_lists_the_changed_blocks() {
+        let old = single_block_chunk();
+        let mut new = old.clone();
+        new.set_block(3, 4, 5, Block { material : GLASS });
+        new.set_block(15, 0, 0, Block { material : STONE });
+        new.set_light(1, 1, 1, 3);
+
+        let diff = old.diff(&new);
+        assert_eq!(diff, vec![
+            (15, 0, 0, Block::default(), Block { material : STONE }),
+            (3, 4, 5, Block { material : STONE }, Block { material : GLASS }),
+        ]);
+        assert!(old.diff(&old.clone()).is_empty());
+    }
+
+    #[test]
+    fn applying_a_diff_turns_one_chunk_into_the_other() {
+        let mut chunk = single_block_chunk();
+        let mut target = Chunk16::empty();
+        target.fill_region(point![0, 0, 0], point![15, 0, 15], STONE);
+        target.set_block(8, 8, 8, Block { material : GLASS });
+
+        let changes : Vec<_> = chunk.diff(&target)
+            .into_iter()
+            .map(|(x, y, z, _, new)| (x, y, z, new))
+            .collect();
+        chunk.clear_change();
+        chunk.changed_edges = [false; 6];
+        chunk.apply_changes(&changes).unwrap();
+
+        assert!(chunk.diff(&target).is_empty());
+        assert_eq!(chunk.solid_count(), target.solid_count());
+        assert!(chunk.has_changed());
+        assert!(chunk.has_changed_edge(AADirection::YNegative));
+        assert!(!chunk.has_changed_edge(AADirection::YPositive));
+
+        // Nothing is written if any of the changes is out of range
+        let mut chunk = Chunk16::empty();
+        let changes = [(0, 0, 0, Block { material : STONE }), (0, 16, 0, Block { material : STONE })];
+        assert_eq!(chunk.apply_changes(&changes), Err(BlockOutOfRange { x : 0, y : 16, z : 0 }));
+        assert!(chunk.is_empty());
+    }
+
+    // Lengths along X of the quads on top of a row of `N` stone blocks
+    fn top_quad_lengths<const N: usize>() -> Vec<u8> {
+        let mut chunk = Chunk::<N, 1, 1>::empty();
+        chunk.fill(STONE);
+        greedy_face_quads(&chunk, &[None; 6], &registry(), &MeshConfig::default())
+            .unwrap()
+            .iter()
+            .filter(|quad| quad.dir == AADirection::YPositive)
+            .map(|quad| quad.size.x)
+            .collect()
+    }
+
+    #[test]
+    fn long_greedy_runs_are_split() {
+        assert_eq!(top_quad_lengths::<255>(), vec![255]);
+        assert_eq!(top_quad_lengths::<256>(), vec![255, 1]);
+        assert_eq!(top_quad_lengths::<300>(), vec![255, 45]);
+
+        let mut chunk = Chunk::<256, 1, 1>::empty();
+        chunk.fill(STONE);
+        let mesh = GreedyChunkMesher::generate_mesh(&chunk, &[None; 6], &registry(), &MaterialAtlas::default())
+            .unwrap()
+            .opaque;
+        let max_x = positions(&mesh).iter().map(|p| p[0]).fold(0f32, f32::max);
+        assert_eq!(max_x, 256f32);
+    }
+
+    #[test]
+    fn naive_mesher_skips_air() {
+        let registry = registry();
+        let atlas = MaterialAtlas::default();
+
+        let chunk = Chunk::<16, 16, 16>::empty();
+        let meshes = NaiveChunkMesher::generate_mesh(&chunk, &[None; 6], &registry, &atlas).unwrap();
+        assert!(positions(&meshes.opaque).is_empty());
+        assert!(indices(&meshes.opaque).is_empty());
+        assert!(positions(&meshes.transparent).is_empty());
+
+        let mut chunk = Chunk::<16, 16, 16>::empty();
+        chunk.set_block(4, 4, 4, Block { material : STONE });
+        let meshes = NaiveChunkMesher::generate_mesh(&chunk, &[None; 6], &registry, &atlas).unwrap();
+        assert_eq!(positions(&meshes.opaque).len(), 6 * 4);
+    }
+
+    #[test]
+    fn point_clouds_have_a_point_per_block() {
+        let mut chunk = Chunk16::empty();
+        chunk.fill_region(point![2, 2, 2], point![4, 3, 2], STONE);
+        chunk.set_block(0, 15, 0, Block { material : GLASS });
+
+        let cloud = generate_point_cloud(&chunk);
+        assert_eq!(cloud.primitive_topology(), PrimitiveTopology::PointList);
+        let points = positions(&cloud);
+        assert_eq!(points.len(), chunk.solid_count());
+        assert_eq!(points.len(), 7);
+        assert!(points.contains(&[0.5f32, 15.5f32, 0.5f32]));
+
+        let colors = colors(&cloud);
+        assert_eq!(colors.len(), 7);
+        // The glass block comes first in index order
+        assert_ne!(colors[0], colors[1]);
+        assert!(colors[1..].iter().all(|color| *color == colors[1]));
+    }
+
+    #[test]
+    fn batched_meshing_matches_per_chunk() {
+        let mut per_chunk = SystemStage::single_threaded();
+        per_chunk.add_system(chunk_meshing_system::<GreedyChunkMesher, 16, 16, 16>.system());
+        let mut batched = SystemStage::single_threaded();
+        batched.add_system(batched_chunk_meshing_system::<GreedyChunkMesher, 16, 16, 16>.system());
+
+        let per_chunk = mesh_row(per_chunk);
+        assert!(per_chunk.iter().all(|positions| !positions.is_empty()));
+        assert_eq!(mesh_row(batched), per_chunk);
+    }
+
+    #[test]
+    fn directions_round_trip_through_axis_and_sign() {
+        for direction in AADirection::iter() {
+            assert_eq!(AADirection::from_axis_sign(direction.axis(), direction.is_positive()), direction);
+            assert_eq!(AADirection::from_axis_sign(direction.axis(), !direction.is_positive()), direction.opposite());
+            assert_eq!(direction.normal()[direction.axis() as usize].abs(), 1f32);
+        }
+        for (index, axis) in Axis::ALL.iter().enumerate() {
+            assert_eq!(*axis as usize, index);
+            assert_eq!(AADirection::from_axis_sign(*axis, true).axis(), *axis);
+        }
+    }
+
+    #[test]
+    fn neighbors_are_marked_once_per_frame() {
+        let mut app = app();
+        let left = app.world.spawn().insert(solid_chunk()).id();
+        let middle = app.world.spawn().insert(solid_chunk()).id();
+        let right = app.world.spawn().insert(solid_chunk()).id();
+        assert!(link_chunks::<16, 16, 16>(&mut app.world, left, middle, AADirection::XPositive));
+        assert!(link_chunks::<16, 16, 16>(&mut app.world, middle, right, AADirection::XPositive));
+        for entity in [left, middle, right] {
+            let mut chunk = app.world.get_mut::<Chunk16>(entity).unwrap();
+            chunk.clear_change();
+            chunk.changed_edges = [false; 6];
+        }
+        let revision = app.world.get::<Chunk16>(middle).unwrap().revision();
+
+        // Both chunks next to the middle one change the blocks along its sides many times
+        for entity in [left, right] {
+            let mut chunk = app.world.get_mut::<Chunk16>(entity).unwrap();
+            for y in 0..16 {
+                for z in 0..16 {
+                    chunk.set_block(0, y, z, Block { material : GLASS });
+                    chunk.set_block(15, y, z, Block { material : GLASS });
+                }
+            }
+        }
+
+        let mut stage = SystemStage::single_threaded();
+        stage.add_system(chunk_neighbor_change_system::<16, 16, 16>.system());
+        stage.run(&mut app.world);
+        let chunk = app.world.get::<Chunk16>(middle).unwrap();
+        assert!(chunk.has_changed());
+        assert_eq!(chunk.revision(), revision + 1);
+    }
+
+    #[test]
+    fn face_visibility_can_draw_internal_faces() {
+        const LEAVES : MaterialID = MaterialID::new(3);
+        let mut registry = registry();
+        registry.register(Material { id : LEAVES, transparent : false, custom_model : false, tint : WHITE });
+        let mut chunk = Chunk::<2, 1, 1>::empty();
+        chunk.fill(LEAVES);
+        let mesh_with = |registry : &MaterialRegistry, chunk : &Chunk<2, 1, 1>| {
+            generate_mesh_with(chunk, &[None; 6], registry, &MaterialAtlas::default(), &NAIVE_CONFIG)
+                .unwrap()
+                .opaque
+        };
+        assert_eq!(positions(&mesh_with(&registry, &chunk)).len(), 10 * 4);
+
+        // Leaves draw the faces between each other, other blocks are culled as usual
+        registry.set_face_visibility(|block, neighbor, _| {
+            (block.material == LEAVES && neighbor.material == LEAVES) || neighbor.is_air()
+        });
+        assert_eq!(positions(&mesh_with(&registry, &chunk)).len(), 12 * 4);
+        chunk.fill(STONE);
+        assert_eq!(positions(&mesh_with(&registry, &chunk)).len(), 10 * 4);
+
+        registry.clear_face_visibility();
+        chunk.fill(LEAVES);
+        assert_eq!(positions(&mesh_with(&registry, &chunk)).len(), 10 * 4);
+    }
+
+    #[cfg(feature = "debug-export")]
+    #[test]
+    fn obj_has_a_line_per_vertex_and_triangle() {
+        let config = MeshConfig { greedy : false, ..MeshConfig::DEFAULT };
+        let mesh = IncrementalChunkMesh::new(&single_block_chunk(), &[None; 6], &registry(), &MaterialAtlas::default(), &config)
+            .unwrap();
+        let obj = mesh.opaque().to_obj();
+        let count = |prefix : &str| obj.lines().filter(|line| line.split(' ').next() == Some(prefix)).count();
+        assert_eq!(count("v"), 24);
+        assert_eq!(count("vn"), 24);
+        assert_eq!(count("vt"), 24);
+        assert_eq!(count("f"), 12);
+        assert!(obj.contains("f 1/1/1 2/2/2 3/3/3\n"));
+    }
+
+    #[test]
+    fn surface_blocks_are_the_shell() {
+        let mut chunk = Chunk16::empty();
+        chunk.fill_region(point![2, 2, 2], point![5, 5, 5], STONE);
+        let surface : Vec<(usize, usize, usize)> = chunk.surface_blocks().map(|(x, y, z, _)| (x, y, z)).collect();
+
+        // The 4x4x4 cube minus its 2x2x2 interior
+        assert_eq!(surface.len(), 4 * 4 * 4 - 2 * 2 * 2);
+        assert!(surface.iter().all(|(x, y, z)| [x, y, z].iter().any(|coord| **coord == 2 || **coord == 5)));
+
+        // Blocks are only exposed to air inside the chunk
+        assert_eq!(solid_chunk().surface_blocks().count(), 0);
+    }
+
+    #[test]
+    fn builder_matches_manual_construction() {
+        let built = ChunkBuilder::<16, 16, 16>::new()
+            .fill_region(point![0, 0, 0], point![15, 1, 15], STONE)
+            .set(8, 2, 8, GLASS)
+            .set(0, 0, 0, AIR.id)
+            .build();
+
+        let mut manual = Chunk16::empty();
+        for (x, y, z, block) in manual.iter_blocks_mut() {
+            if y <= 1 && (x, y, z) != (0, 0, 0) {
+                block.material = STONE;
             }
         }
+        manual.block_at_mut(8, 2, 8).material = GLASS;
+
+        assert!(built.diff(&manual).is_empty());
+        assert_eq!(built.solid_count(), 16 * 16 * 2);
+        assert!(built.has_changed());
+        assert_eq!(ChunkBuilder::<4, 4, 4>::default().fill(STONE).build().solid_count(), 4 * 4 * 4);
+    }
+
+    #[cfg(feature = "debug-export")]
+    #[test]
+    fn debug_triangles_have_their_own_colors() {
+        let config = MeshConfig { ambient_occlusion : false, ..MeshConfig::DEFAULT };
+        let mesh = generate_triangle_debug_mesh(&single_block_chunk(), &[None; 6], &registry(), &config).unwrap();
+
+        // Every triangle has its own three vertices in one color
+        let (vertices, colors) = (positions(&mesh), colors(&mesh));
+        assert_eq!(vertices.len(), 6 * 2 * 3);
+        assert_eq!(indices(&mesh), (0..6 * 2 * 3).collect::<Vec<u32>>());
+        for (index, triangle) in colors.chunks_exact(3).enumerate() {
+            assert!(triangle.iter().all(|color| *color == debug_index_color(index as u32)));
+        }
+
+        // The two triangles of a face differ
+        for face in colors.chunks_exact(6) {
+            assert_ne!(face[0], face[3]);
+        }
+    }
+
+    #[test]
+    fn iter_neighbors_skips_missing_links() {
+        let mut world = World::new();
+        let (below, front) = (world.spawn().id(), world.spawn().id());
+        let mut chunk = Chunk16::empty();
+        assert_eq!(chunk.iter_neighbors().count(), 0);
 
-        mesh.into()
+        chunk.set_neighbor(below, AADirection::YNegative);
+        chunk.set_neighbor(front, AADirection::ZPositive);
+        let neighbors : Vec<(AADirection, Entity)> = chunk.iter_neighbors().collect();
+        assert_eq!(neighbors, vec![(AADirection::YNegative, below), (AADirection::ZPositive, front)]);
     }
 }